@@ -28,6 +28,11 @@ readme: {
 
 			3. Result: A new file `MyScript.sh` is created instantly.
 			"""
+		importing: """
+			`tmplr import cookiecutter path/to/cookiecutter-template python-lib.tmplr` converts a cookiecutter template into a `.tmplr` file, or into a directory template when the output doesn't end with `.tmplr`. Without output it writes `<DIR name>.tmplr` into the current directory. Existing output isn't overwritten.
+			`{{cookiecutter.x}}` becomes `{{ x }}`, and variables from `cookiecutter.json` (with their `__prompts__`) become `VAR` declarations.
+			Other Jinja (`{% if %}`, filters like `| upper`, method calls), dictionary variables, `_` options and hooks can't be converted and are reported, so they can be fixed by hand.
+			"""
 		todo: """
 			- [ ] Add filters for variables (upper/lower)
 			- [ ] Add special variables (for creating things based on paths, e.g. for Elixir `alpha/bravo/charlie/delta.ex` would be `defmodule Alpha.Bravo.Charlie.Delta`)
@@ -50,6 +55,12 @@ readme: {
 			```
 
 			This "script" is a `script.tmplr` template that I can use to easily produce files from this template using a simple `tmplr script MyScriptName` command.
+
+			Templates can also be directories, e.g. `~/.config/tmplr/rust-cli/`, holding real files whose names and contents use `{{ variables }}`. Each file works like a `FILE` section and empty directories like `DIR` ones, so every file can be edited with proper editor support.
+			A `tmplr.toml` in such directory marks it as a template and describes it with the same keys as front matter (`description`, `tags`, ...); it isn't copied.
+			Library directories without `tmplr.toml` just group templates, and hidden ones like `.git` are skipped.
+			`[vars.NAME]` tables in `tmplr.toml` declare variables like `VAR` sections, with `default`, `prompt` and `choices` keys.
+			`tmplr echo rust-cli` shows the directory as an equivalent `.tmplr` template.
 			"""
 		cli: """
 			Once you have your .tmplr you can simply:
@@ -98,23 +109,131 @@ readme: {
 			```
 
 			There is no template functions or anything whatsoever. Only simple substitution!
+
+			Go's txtar archives can be used directly: `tmplr make fixture.txtar app` treats every `-- path --` file as a `FILE` section, and the archive comment as preamble.
+			`tmplr convert fixture.txtar > fixture.tmplr` and `tmplr convert fixture.tmplr > fixture.txtar` convert between both formats, guessing them from the input extension; `--from` and `--to` set them explicitly. Sections other than `FILE` and attributes of `FILE` sections can't be stored in txtar and are skipped with a warning.
+
+			Markdown files (`.md`, `.markdown`) work as templates too: fenced code blocks annotated with a path, e.g. ```` ```rust file=src/main.rs ````, become `FILE` sections, and other attributes of the block (`mode=755`, `if=ci`) are passed along. Prose and code blocks without `file=` are documentation, so onboarding docs can double as runnable scaffolds: `tmplr make docs/new-service.md billing`.
 			"""
 		section_types: """
 			`.tmplr` files use specific headers to define how files are generated.
 
+			Section content starts on the line after the header and ends right before the next header. It is written exactly as is, including leading blank lines, indentation and final newline.
+
+			Files are generated in the directory `tmplr` runs in, or in `make --output DIR`; `--into-name` puts them in a new `{{ name }}/` directory inside of it, e.g. `tmplr make -o services --into-name rust-lib billing`. Paths of generated files have to stay inside that directory, also after variables are filled in. Absolute paths, `..`, NUL characters, reserved names (`CON`, `nul.txt`, names ending with a dot or space), `.git` directories and existing symlinks leading outside are rejected with an error, so third-party templates can't write anywhere else.
+
+			Nothing is written until every section has been rendered. Changes are staged first and then moved into place one file at a time; if anything fails on the way, or the run is interrupted with Ctrl-C, changes made so far are rolled back and the target directory is left as it was.
+
+			`FILE` sections overwrite existing files by default. To protect local edits, pass `make --on-conflict=skip` to keep changed files, `error` to stop without writing anything, `rename` to write the generated file next to it as `path.new` (or `path.new.1`... when that's taken), or `prompt` to see a diff and choose to overwrite, skip, or overwrite all remaining files. Files whose content matches the template aren't conflicts.
+
+			To review changes before making them, `make --diff` goes through the template without writing anything and prints each touched path as `new`, `modified`, `unchanged` or `removed`, followed by a unified diff against what's on disk (colored on a terminal).
+
+			Every `make` records what it did in `.tmplr/manifest.toml` inside the output directory: the `tmplr` version, and for each template made there its name, path and content hash, the variables used and a hash of each file it wrote. Values of variables declared with `secret=true`, or named like a secret (`token`, `password`, `api_key`...), are masked. The manifest is committed together with the files, so it's only written when `make` succeeds. Templates layered onto the output of another one get their own record next to it; making the same template again replaces its record. Templates from the templates directory are recorded under their name there (`rust/cli`), others under their full path.
+
+			`tmplr update` brings a generated directory up to date with a newer version of its template. It re-renders each template recorded in the manifest, in the order they were made, with its recorded variables, then three-way merges each `FILE` with what the template generated last time, kept under `.tmplr/base`, and the file as it is now. Local changes and template changes to different lines are both kept; lines changed on both sides get `<<<<<<< local`, `=======` and `>>>>>>> template` markers to resolve by hand. Files rendering a secret value have no base copy, so secrets stay out of `.tmplr`; they're replaced while they still match the hash in the manifest, otherwise the whole file is marked as a conflict. `EXT`, `PREPEND`, `INSERT` and `PATCH` edits were applied by `make` already and are skipped, as are `RUN` sections; `MERGE`, `BLOCK` and the rest are applied again as `make` would apply them. Masked variables have to be passed again as `VAR=VAL`, as do new variables with `--yes`; a template given explicitly is used in place of the only recorded one, or of the one recorded under its name.
+
 			* **Preamble (Comments)**
 			    Any text appearing before the first section header is treated as a comment. Use this space to document your template.
 
+			* **Comments**
+			    `{### # note ###}` and `{#- note -#}` are dropped when rendering and never reach the output. Use them to explain parts of large templates.
+			    * `{### # note ###}` doesn't end the current section. When it is alone on its line, the whole line is dropped.
+			    * `{#- ... -#}` can span lines and sit anywhere in section content. Lines holding only a comment are removed entirely.
+			    * Templates producing Jinja or similar files, where `{#-` is part of the content, can pick other markers with `{### DELIMITERS comments="<# #>" ###}`. `create` does that on its own when some file contains `{#-`.
+
 			* `{### FILE path/to/name ###}`
 			    **Overwrites** the target file. If the file exists, its content is completely replaced by the template content.
+			    * Regions between `tmplr:keep begin ID` and `tmplr:keep end` comment lines (e.g. `// tmplr:keep begin methods`) are carried over from the file being overwritten, so hand-written code survives regenerating it.
 
 			* `{### EXT path/to/name ###}`
 			    **Appends** to the target file.
 			    * It checks for duplicates: content is only appended if it is not already present.
 			    * If the file does not exist, it behaves exactly like `FILE`.
 
+			* `{### PREPEND path/to/name ###}`
+			    **Prepends** to the target file, e.g. license headers or `use` lines.
+			    * Like `EXT`, content is only added if it is not already present, and a missing file is created.
+
+			* `{### INSERT path/to/name after="// tmplr:routes" ###}`
+			    **Inserts** content next to an anchor line of an existing file (`before=...` puts it above the line).
+			    * Anchor is a substring of the line, or a regular expression when written as `'/regex/'` (single quotes keep backslashes intact).
+			    * First matching line is used, `at=last` picks the last one.
+			    * Inserted lines inherit the anchor's indentation. Content that is already present isn't inserted again.
+			    * Fails if the file or the anchor line doesn't exist.
+
+			* `{### PATCH path/to/name ###}`
+			    **Applies unified diff** (e.g. output of `git diff`) to an existing file. Variables are rendered in the diff before applying.
+			    * Hunks are matched near their original position; whitespace differences and up to `fuzz` (default 2) context lines at hunk edges are tolerated.
+			    * When a hunk can't be applied, `tmplr` exits with an error without changing any files, and saves the whole patch, applicable hunks included, to `path/to/name.rej`.
+
+			* `{### MERGE Cargo.toml ###}`
+			    **Deep-merges** the section body into an existing JSON, TOML or YAML document, e.g. to add dependencies, workspace members or npm scripts.
+			    * Tables/objects/mappings are merged key by key, missing array items are appended. Existing formatting and comments are kept.
+			    * Format comes from the file extension (`.json`, `.toml`, `.yaml`, `.yml`), or `format=json|toml|yaml`.
+			    * `conflict=keep|override|error` decides what happens when both sides set a different value; `keep` (default) leaves existing value and warns.
+			    * If the file does not exist, it behaves exactly like `FILE`.
+
+			* `{### BLOCK .gitignore id=rust ###}`
+			    **Manages a block** of the target file between `# >>> tmplr:rust` and `# <<< tmplr:rust` marker lines. Running the template again replaces what's between the markers instead of adding another copy.
+			    * Markers use the file's comment syntax (`//` for `.rs`, `<!-- -->` for `.html`, `#` by default); `comment="--"` sets it explicitly.
+			    * Block is appended if markers aren't found, a missing file is created.
+
+			* `{### REMOVE path/to/name ###}`
+			    **Removes** a file or directory, e.g. when a template migrates an existing project. Nothing happens if it's already gone.
+
+			* `{### MOVE path/to/old -> path/to/new ###}`
+			    **Moves** a file or directory, creating missing parent directories. Skipped if the source is gone and the target exists.
+			    * `REMOVE` and `MOVE` ask for confirmation; pass `make --yes` to skip the prompt. Dry run only lists them.
+
+			* `{### RUN ###}`
+			    **Runs shell commands** from the section body once all files are generated, e.g. `cargo fmt` or `git init`. Commands run in the output root with `sh -c` (`cmd /C` on Windows).
+			    * Variables are available as `TMPLR_*` environment variables, e.g. `TMPLR_NAME`; other characters than letters and digits become `_`.
+			    * `{{ variables }}` in the body are shell-quoted when they contain anything but letters, digits and `_-./,:=+@%`, so `git init {{ name }}` stays one argument. Inside quoted strings use the environment variables instead.
+			    * Templates from outside your templates directory ask before running commands, before any file is written (`make --yes` to trust them). `make --no-hooks` skips `RUN` sections.
+			    * A failing command stops `tmplr` and shows its output.
+
+			* `{### REQUIRE exists Cargo.toml ###}`
+			    **Checks a precondition** before anything is written. If any check fails, `tmplr` lists the reasons and stops.
+			    * `exists PATH...` / `missing PATH...` - files or directories which have to exist or be absent, relative to the output directory.
+			    * `command NAME...` - commands which have to be on `PATH`.
+			    * `var NAME [VALUE]` - variable has to be set, or equal `VALUE` (`/regex/` matches the whole value).
+			    * `tmplr VERSION` - minimal `tmplr` version, e.g. `{### REQUIRE tmplr 0.0.10 ###}`.
+			    * `message="..."` attribute replaces the reasons of a failed section.
+
+			* `{### META description="Rust CLI tool" tags=rust,cli version=1.2 author=me ###}`
+			    **Describes the template**. Put it before other sections. Description and tags are shown by `tmplr list` and in the template picker, `tmplr info TEMPLATE` shows all of it.
+			    TOML front matter between `+++` lines at the very top of the file works too:
+
+			        +++
+			        description = "Rust CLI tool"
+			        tags = ["rust", "cli"]
+			        +++
+
+			* `{### VAR license default=MIT prompt="License" choices=MIT,Apache-2.0 ###}`
+			    **Declares a variable** the template uses. When it isn't given as `license=...`, `tmplr` asks for it on a terminal, otherwise takes `default`.
+			    * `default` can use variables declared before, e.g. `default="{{ name }}-cli"`. Without `default` (or `choices`) the variable has to be given.
+			    * `choices` lists allowed values, the first one is the default.
+			    * `secret=true` keeps the value out of `.tmplr/manifest.toml`.
+
 			* `{### DIR path/to/dir ###}`
 			    **Creates an empty directory**. This section does not support content/body text.### .tmplr sections
+
+			* **Attributes**
+			    Headers can carry `key=value` options after the path, e.g. `{### FILE "path with spaces.txt" mode=644 if=ci ###}`.
+			    Quote paths and values containing spaces with `"` or `'`. Attribute values can use `{{ variables }}`.
+			    * `if=VAR` / `if=!VAR` - only process the section when `VAR` is set to a truthy value (or isn't, when negated). Supported by all sections.
+			    * `mode=755` - octal permissions applied to the created file or directory (`FILE`, `DIR`).
+			    * `trim=true` - strip leading and trailing whitespace from the rendered content (`FILE`, `EXT`).
+			    * `final_newline=true|false` - make sure content ends with a newline, or remove trailing newlines (`FILE`, `EXT`).
+			    * `eol=lf|crlf|native` - normalize line endings of the content (`FILE`, `EXT`). `make --eol` sets it for all sections which don't specify it.
+			    * `bom=true` - start the file with UTF-8 byte order mark (`FILE`).
+			    * `once=true` - only write the file when it doesn't exist yet, e.g. for `.env` or local config (`FILE`).
+			    Unknown attributes are reported as warnings.
+
+			* `{### DELIMITERS <% %> ###}`
+			    Changes variable delimiters for the rest of the template, e.g. for Helm charts or Mustache files where `{{` is everywhere.
+			    Section delimiters can be changed too: `{### DELIMITERS sections="<@ @>" ###}` makes following headers look like `<@ FILE path @>`.
+			    A single section can use its own variable delimiters with `delims` attribute: `{### FILE chart.yaml delims="[[ ]]" ###}`.
 			"""
 	}
 	full: """
@@ -162,6 +281,10 @@ readme: {
 
   \(sections.templates_directory)
 
+  ## Importing cookiecutter templates
+
+  \(sections.importing)
+
   # TODO
 
   \(sections.todo)
@@ -1,3 +1,4 @@
+const VERSION: &str = "0.0.10";
 const HELP: &str = "
 tmplr (v0.0.10)
 
@@ -14,6 +15,22 @@ Usage:
 	        for local templates.
 
 	        --dry-run/-n	don't materialize, only print to stdout
+	        --diff	don't materialize, show what would change as a diff against existing files
+	        -o/--output <DIR>	write into DIR instead of current directory
+	        --into-name	wrap generated files in {{ name }}/ directory
+	        --eol <lf|crlf|native>	line endings for generated files, unless set by eol= attribute
+	        --on-conflict <POLICY>	when FILE target differs: overwrite (default), skip, error, prompt or rename
+	        --yes/-y	don't ask for confirmation (REMOVE, MOVE, hooks of templates outside templates dir)
+	        --no-hooks	don't run RUN sections
+
+	update  [TEMPLATE_FILE/TEMPLATE_NAME] VAR=VAL...
+
+	        Re-apply templates recorded in .tmplr/manifest.toml with their
+	        recorded variables, three-way merging local changes.
+
+	        --diff	don't materialize, show what would change as a diff against existing files
+	        -o/--output <DIR>	update DIR instead of current directory
+	        --yes/-y	don't ask for confirmation (new variables, REMOVE, MOVE)
 
 	create  <TEMPLATE_FILE> <NAME>
 
@@ -25,6 +42,21 @@ Usage:
 
 	list    List available templates
 
+	info    <TEMPLATE_FILE/TEMPLATE_NAME>
+
+	        Show template metadata (description, tags, version, author).
+
 	echo    Echo unexpanded to STDOUT
 
+	convert [--from tmplr|txtar] [--to tmplr|txtar] <FILE/TEMPLATE_NAME>
+
+	        Convert between txtar archives and templates, print to STDOUT.
+	        Formats default to the input extension and the other format.
+
+	import  cookiecutter <DIR> [OUTPUT.tmplr/OUTPUT_DIR]
+
+	        Convert cookiecutter template into a template file or directory,
+	        report Jinja which can't be converted. Writes <DIR name>.tmplr
+	        into the current directory when OUTPUT isn't given.
+
 ";
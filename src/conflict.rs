@@ -0,0 +1,108 @@
+use std::{
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+use crate::{
+    patch,
+    three_way::{self, Merged},
+};
+
+/// What `FILE` does when its target exists with different content
+/// (`--on-conflict`). Existing files are overwritten by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Policy {
+    #[default]
+    Overwrite,
+    Skip,
+    Error,
+    Prompt,
+    Rename,
+    /// Three-way merge against what the template generated before, used by
+    /// `update` only.
+    Merge,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "overwrite" => Ok(Policy::Overwrite),
+            "skip" => Ok(Policy::Skip),
+            "error" => Ok(Policy::Error),
+            "prompt" => Ok(Policy::Prompt),
+            "rename" => Ok(Policy::Rename),
+            _ => Err(format!(
+                "Invalid on-conflict value: {} (expected overwrite, skip, error, prompt or rename)",
+                s
+            )),
+        }
+    }
+}
+
+/// Where generated content of a conflicting file goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    /// Existing file is kept, generated one is written next to it.
+    Rename(String),
+    /// Local changes merged into generated content.
+    Merged(Merged),
+}
+
+/// Suffix of generated files written next to conflicting ones.
+pub const RENAME_SUFFIX: &str = ".new";
+
+/// Resolves conflict of `path` according to `policy`. Choosing "all" in
+/// prompt turns the policy into overwrite for the rest of the template.
+/// `base` is what the template generated last time, if it's known.
+pub fn resolve(
+    path: &str,
+    existing: &str,
+    content: &str,
+    base: Option<&str>,
+    policy: &mut Policy,
+) -> io::Result<Resolution> {
+    match policy {
+        Policy::Merge => Ok(Resolution::Merged(three_way::merge(
+            base.unwrap_or_default(),
+            existing,
+            content,
+        ))),
+        Policy::Overwrite => Ok(Resolution::Overwrite),
+        Policy::Skip => Ok(Resolution::Skip),
+        Policy::Rename => Ok(Resolution::Rename(format!("{}{}", path, RENAME_SUFFIX))),
+        Policy::Error => Err(io::Error::other(format!(
+            "{} already exists and differs from template, pass --on-conflict=overwrite|skip|prompt|rename",
+            path
+        ))),
+        Policy::Prompt => {
+            let diff = patch::format_diff(path, path, &patch::diff(existing, content, 3));
+            match io::stdout().is_terminal() {
+                true => print!("{}", patch::colorize(&diff)),
+                false => print!("{}", diff),
+            }
+            let choice = dialoguer::Select::new()
+                .with_prompt(format!("{} has local changes", path))
+                .items(["Overwrite", "Skip", "Overwrite all"])
+                .default(1)
+                .interact()
+                .map_err(|_| {
+                    io::Error::other(format!(
+                        "Can't ask about {}, pass --on-conflict=overwrite|skip|error|rename",
+                        path
+                    ))
+                })?;
+            match choice {
+                0 => Ok(Resolution::Overwrite),
+                1 => Ok(Resolution::Skip),
+                _ => {
+                    *policy = Policy::Overwrite;
+                    Ok(Resolution::Overwrite)
+                }
+            }
+        }
+    }
+}
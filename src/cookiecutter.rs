@@ -0,0 +1,420 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ImportArgs,
+    dir_template::MANIFEST,
+    error_handling::{UnwrapReport, quit_with_error},
+    header::{Attributes, quote},
+    merge::json,
+    template::{CLOSE, EXTENSION, OPEN, write_template},
+};
+
+const CONFIG: &str = "cookiecutter.json";
+const PREFIX: &str = "cookiecutter.";
+
+pub fn run_import(args: &ImportArgs) {
+    if args.kind != "cookiecutter" {
+        quit_with_error(
+            1,
+            &format!(
+                "Unknown import source: {} (expected cookiecutter)",
+                args.kind
+            ),
+        );
+    }
+    let import = Import::read(&args.source).unwrap_or_report(1);
+    // Template file named after the cookiecutter template by default
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => PathBuf::from(&import.name).with_added_extension(EXTENSION),
+    };
+    let written = match output.extension().is_some_and(|ext| ext == EXTENSION) {
+        true => import.write_template(&output),
+        false => import.write_dir(&output),
+    };
+    written.unwrap_or_report(1);
+    for report in &import.reports {
+        eprintln!("WARN: {}", report);
+    }
+    println!(
+        "Imported {} files into {}",
+        import.files.len(),
+        output.display()
+    );
+}
+
+/// Cookiecutter template rewritten for tmplr. Things which couldn't be
+/// converted are listed in `reports`.
+#[derive(Debug, Default)]
+struct Import {
+    name: String,
+    vars: Vec<(String, Attributes)>,
+    dirs: Vec<String>,
+    files: Vec<(String, String)>,
+    reports: Vec<String>,
+}
+
+impl Import {
+    fn read(source: &Path) -> io::Result<Import> {
+        let config_path = source.join(CONFIG);
+        let config = fs::read_to_string(&config_path).map_err(|e| {
+            io::Error::other(format!("Can't read {}: {}", config_path.display(), e))
+        })?;
+        let Value::Object(config) = Value::read(&config)? else {
+            return Err(io::Error::other(format!("{} isn't an object", CONFIG)));
+        };
+        let mut import = Import {
+            name: fs::canonicalize(source)?
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            ..Import::default()
+        };
+        import.read_vars(&config);
+
+        let root = template_root(source)?;
+        let root_name = root.file_name().unwrap_or_default().to_string_lossy();
+        let root_name = import.rewrite(&root_name, "directory name");
+        import.read_dir(&root, &root_name)?;
+        if source.join("hooks").is_dir() {
+            import
+                .reports
+                .push("hooks/ aren't converted, add RUN sections instead".into());
+        }
+        Ok(import)
+    }
+
+    fn read_vars(&mut self, config: &[(String, Value)]) {
+        let prompts = config
+            .iter()
+            .find(|(key, _)| key == "__prompts__")
+            .map(|(_, value)| value);
+        for (key, value) in config {
+            if key.starts_with('_') {
+                if key != "__prompts__" {
+                    self.reports
+                        .push(format!("{}: `{}` isn't supported, skipping", CONFIG, key));
+                }
+                continue;
+            }
+            let mut attrs = Attributes::default();
+            match value {
+                Value::String(default) => {
+                    let location = format!("default of `{}`", key);
+                    let before = self.reports.len();
+                    let default = self.rewrite(default, &location);
+                    match self.reports.len() == before {
+                        true => attrs.insert("default", &default),
+                        false => self
+                            .reports
+                            .push(format!("`{}` is left without default value", key)),
+                    }
+                }
+                Value::Scalar(default) => attrs.insert("default", default),
+                Value::List(items) => {
+                    let choices: Vec<&str> = items
+                        .iter()
+                        .filter_map(|item| match item {
+                            Value::String(s) | Value::Scalar(s) => Some(s.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    if choices.len() != items.len() || choices.iter().any(|c| c.contains(',')) {
+                        self.reports.push(format!(
+                            "{}: choices of `{}` have to be plain values without commas, skipping",
+                            CONFIG, key
+                        ));
+                        continue;
+                    }
+                    attrs.insert("choices", &choices.join(","));
+                }
+                Value::Object(_) => {
+                    self.reports.push(format!(
+                        "{}: dictionary variable `{}` isn't supported, skipping",
+                        CONFIG, key
+                    ));
+                    continue;
+                }
+            }
+            let prompt = match prompts {
+                Some(Value::Object(prompts)) => prompts.iter().find(|(k, _)| k == key),
+                _ => None,
+            };
+            match prompt.map(|(_, prompt)| prompt) {
+                Some(Value::String(prompt)) => attrs.insert("prompt", prompt),
+                Some(Value::Object(prompt)) => {
+                    if let Some((_, Value::String(prompt))) =
+                        prompt.iter().find(|(k, _)| k == "__prompt__")
+                    {
+                        attrs.insert("prompt", prompt);
+                    }
+                }
+                _ => (),
+            }
+            self.vars.push((key.clone(), attrs));
+        }
+    }
+
+    fn read_dir(&mut self, dir: &Path, relative: &str) -> io::Result<()> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        entries.sort();
+        if entries.is_empty() {
+            self.dirs.push(relative.into());
+        }
+        for entry in entries {
+            let name = entry.file_name().unwrap_or_default().to_string_lossy();
+            let path = format!("{}/{}", relative, self.rewrite(&name, relative));
+            if entry.is_dir() {
+                self.read_dir(&entry, &path)?;
+                continue;
+            }
+            match fs::read_to_string(&entry) {
+                Ok(content) => {
+                    let content = self.rewrite(&content, &path);
+                    self.files.push((path, content));
+                }
+                Err(_) => self
+                    .reports
+                    .push(format!("{}: not a text file, skipping", path)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns `{{ cookiecutter.x }}` into `{{ x }}`. Other Jinja is kept as is
+    /// and reported.
+    fn rewrite(&mut self, text: &str, location: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut cursor = 0;
+        while let Some(start) = find_jinja(&text[cursor..]).map(|n| cursor + n) {
+            result.push_str(&text[cursor..start]);
+            let close = match &text[start..start + 2] {
+                "{{" => "}}",
+                "{%" => "%}",
+                _ => "#}",
+            };
+            let Some(end) = text[start..].find(close).map(|n| start + n + 2) else {
+                cursor = start;
+                break;
+            };
+            let tag = &text[start..end];
+            match variable(tag) {
+                Some(variable) => result.push_str(&variable),
+                None => {
+                    let line = text[..start].matches('\n').count() + 1;
+                    let shown = tag.lines().next().unwrap_or(tag);
+                    self.reports.push(format!(
+                        "{}:{}: unsupported Jinja `{}`",
+                        location, line, shown
+                    ));
+                    result.push_str(tag);
+                }
+            }
+            cursor = end;
+        }
+        result.push_str(&text[cursor..]);
+        result
+    }
+
+    fn write_template(&self, output: &Path) -> io::Result<()> {
+        if output.exists() {
+            return Err(io::Error::other(format!(
+                "{} already exists",
+                output.display()
+            )));
+        }
+        let mut preamble = format!("Imported from cookiecutter template {}\n", self.name);
+        for (name, attrs) in &self.vars {
+            match attrs.is_empty() {
+                true => preamble.push_str(&format!("{} VAR {} {}\n", OPEN, quote(name), CLOSE)),
+                false => preamble.push_str(&format!(
+                    "{} VAR {} {} {}\n",
+                    OPEN,
+                    quote(name),
+                    attrs,
+                    CLOSE
+                )),
+            }
+        }
+        fs::write(output, write_template(&preamble, &self.dirs, &self.files))
+    }
+
+    fn write_dir(&self, output: &Path) -> io::Result<()> {
+        if output.exists() {
+            return Err(io::Error::other(format!(
+                "{} already exists",
+                output.display()
+            )));
+        }
+        for dir in &self.dirs {
+            fs::create_dir_all(output.join(dir))?;
+        }
+        for (path, content) in &self.files {
+            let path = output.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        let mut manifest = format!("# Imported from cookiecutter template {}\n", self.name);
+        for (name, attrs) in &self.vars {
+            manifest.push_str(&format!("\n[vars.{}]\n", name));
+            for key in attrs.keys() {
+                let value = attrs.get(key).unwrap_or_default();
+                let value = match key {
+                    "choices" => {
+                        let items: Vec<String> = value.split(',').map(toml_string).collect();
+                        format!("[{}]", items.join(", "))
+                    }
+                    _ => toml_string(value),
+                };
+                manifest.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+        fs::write(output.join(MANIFEST), manifest)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn find_jinja(text: &str) -> Option<usize> {
+    ["{{", "{%", "{#"]
+        .iter()
+        .filter_map(|open| text.find(open))
+        .min()
+}
+
+/// `{{ cookiecutter.x }}` as tmplr variable, `None` for anything else,
+/// filters included, since tmplr has none.
+fn variable(tag: &str) -> Option<String> {
+    let inner = tag.strip_prefix("{{")?.strip_suffix("}}")?;
+    let inner = inner.strip_prefix('-').unwrap_or(inner);
+    let inner = inner.strip_suffix('-').unwrap_or(inner);
+    let name = inner.trim().strip_prefix(PREFIX)?;
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(format!("{{{{ {} }}}}", name))
+}
+
+/// Cookiecutter renders the single directory with templated name.
+fn template_root(source: &Path) -> io::Result<PathBuf> {
+    let mut roots = fs::read_dir(source)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().contains("{{"))
+        })
+        .collect::<Vec<_>>();
+    match roots.len() {
+        1 => Ok(roots.remove(0)),
+        _ => Err(io::Error::other(format!(
+            "Expected one directory like {{{{cookiecutter.project_slug}}}} in {}",
+            source.display()
+        ))),
+    }
+}
+
+/// JSON value; numbers, booleans and null are kept as written.
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Scalar(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Reads config parsed by the JSON parser shared with `MERGE`.
+    fn read(src: &str) -> io::Result<Value> {
+        let node = json::parse(src).map_err(|e| io::Error::other(format!("{}: {}", CONFIG, e)))?;
+        Ok(Value::from_node(&node, src))
+    }
+
+    fn from_node(node: &json::Node, src: &str) -> Value {
+        let text = &src[node.start..node.end];
+        match &node.value {
+            json::Value::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|m| (json::unescape(&m.key), Value::from_node(&m.value, src)))
+                    .collect(),
+            ),
+            json::Value::Array(items) => {
+                Value::List(items.iter().map(|i| Value::from_node(i, src)).collect())
+            }
+            json::Value::Scalar if text.starts_with('"') => Value::String(json::unescape(text)),
+            json::Value::Scalar => Value::Scalar(text.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_variables_and_reports_jinja() {
+        let mut import = Import::default();
+        let rewritten = import.rewrite(
+            "name = \"{{cookiecutter.project_slug}}\"\n{% if cookiecutter.cli %}\nNAME={{ cookiecutter.name|upper }} {{ cookiecutter.name.lower() }}\n",
+            "pyproject.toml",
+        );
+        assert_eq!(
+            rewritten,
+            "name = \"{{ project_slug }}\"\n{% if cookiecutter.cli %}\nNAME={{ cookiecutter.name|upper }} {{ cookiecutter.name.lower() }}\n"
+        );
+        assert_eq!(
+            import.reports,
+            vec![
+                "pyproject.toml:2: unsupported Jinja `{% if cookiecutter.cli %}`",
+                "pyproject.toml:3: unsupported Jinja `{{ cookiecutter.name|upper }}`",
+                "pyproject.toml:3: unsupported Jinja `{{ cookiecutter.name.lower() }}`",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_config_variables() -> io::Result<()> {
+        let config = Value::read(
+            r#"{
+  "project_name": "My \"App\"",
+  "project_slug": "{{ cookiecutter.project_name.lower() }}",
+  "license": ["MIT", "BSD-3"],
+  "use_docker": true,
+  "_copy_without_render": ["*.html"],
+  "__prompts__": {"project_name": "Project name", "license": {"__prompt__": "License"}}
+}"#,
+        )?;
+        let Value::Object(config) = config else {
+            panic!("expected object");
+        };
+        let mut import = Import::default();
+        import.read_vars(&config);
+        let vars: Vec<String> = import
+            .vars
+            .iter()
+            .map(|(name, attrs)| format!("{} {}", name, attrs))
+            .collect();
+        assert_eq!(
+            vars,
+            vec![
+                r#"project_name default="My \"App\"" prompt="Project name""#,
+                "project_slug ",
+                "license choices=MIT,BSD-3 prompt=License",
+                "use_docker default=true",
+            ]
+        );
+        assert_eq!(import.reports.len(), 3);
+        Ok(())
+    }
+}
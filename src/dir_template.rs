@@ -0,0 +1,202 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use toml_edit::DocumentMut;
+
+use crate::{
+    header::{Attributes, quote},
+    meta::toml_value,
+    template::{CLOSE, OPEN, write_template},
+};
+
+/// Manifest marking directory template, TOML with the same keys as
+/// template front matter (`description`, `tags`, ...).
+pub const MANIFEST: &str = "tmplr.toml";
+
+/// Version control metadata of a checked out template isn't part of it.
+const VCS_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+/// Whether library directory is a template by itself rather than a group of
+/// templates. Only directories with a manifest are, so that `.git`, `docs`
+/// and the like in the library aren't taken for templates.
+pub fn is_template_dir(path: &Path) -> bool {
+    path.join(MANIFEST).is_file()
+}
+
+/// Turns directory template into `.tmplr` text: manifest becomes front
+/// matter, files become `FILE` sections and empty directories `DIR` ones.
+pub fn read(dir: &Path) -> io::Result<String> {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    collect(dir, Path::new(""), &mut files, &mut dirs)?;
+
+    let mut contents = Vec::with_capacity(files.len());
+    for path in &files {
+        let content = fs::read_to_string(dir.join(path)).map_err(|e| {
+            io::Error::other(format!(
+                "Can't read {} as text: {}",
+                dir.join(path).display(),
+                e
+            ))
+        })?;
+        contents.push((slashed(path), content));
+    }
+
+    let preamble = match fs::read_to_string(dir.join(MANIFEST)) {
+        Ok(manifest) => manifest_preamble(&manifest)?,
+        Err(_) => String::new(),
+    };
+    let dirs: Vec<String> = dirs.iter().map(|d| slashed(d)).collect();
+    Ok(write_template(&preamble, &dirs, &contents))
+}
+
+/// Top-level manifest keys become front matter, `[vars.NAME]` tables become
+/// `VAR` sections with the table's keys as attributes.
+fn manifest_preamble(manifest: &str) -> io::Result<String> {
+    let manifest: DocumentMut = manifest
+        .parse()
+        .map_err(|e| io::Error::other(format!("Invalid {}: {}", MANIFEST, e)))?;
+    let mut front_matter = DocumentMut::new();
+    let mut vars: Vec<(String, Attributes)> = Vec::new();
+    for (key, item) in manifest.iter() {
+        match (key, item.as_table_like()) {
+            ("vars", Some(table)) => {
+                for (name, var) in table.iter() {
+                    let mut attrs = Attributes::default();
+                    for (attr, value) in var.as_table_like().into_iter().flat_map(|t| t.iter()) {
+                        if let Some(value) = value.as_value() {
+                            attrs.insert(attr, &toml_value(value));
+                        }
+                    }
+                    vars.push((name.into(), attrs));
+                }
+            }
+            _ if item.is_value() => {
+                front_matter.insert(key, item.clone());
+            }
+            _ => eprintln!("WARN: {}: unknown table [{}], ignoring it", MANIFEST, key),
+        }
+    }
+
+    let mut result = String::new();
+    let front_matter = front_matter.to_string();
+    if !front_matter.trim().is_empty() {
+        result = format!("+++\n{}\n+++\n", front_matter.trim());
+    }
+    for (name, attrs) in vars {
+        match attrs.is_empty() {
+            true => result.push_str(&format!("{} VAR {} {}\n", OPEN, quote(&name), CLOSE)),
+            false => result.push_str(&format!(
+                "{} VAR {} {} {}\n",
+                OPEN,
+                quote(&name),
+                attrs,
+                CLOSE
+            )),
+        }
+    }
+    Ok(result)
+}
+
+fn collect(
+    root: &Path,
+    relative: &Path,
+    files: &mut Vec<PathBuf>,
+    dirs: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(root.join(relative))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    if entries.is_empty() && relative != Path::new("") {
+        dirs.push(relative.to_path_buf());
+    }
+    for entry in entries {
+        let Some(name) = entry.file_name() else {
+            continue;
+        };
+        let path = relative.join(name);
+        if entry.is_dir() && VCS_DIRS.iter().any(|vcs| name == *vcs) {
+            continue;
+        } else if entry.is_dir() {
+            collect(root, &path, files, dirs)?;
+        } else if path != Path::new(MANIFEST) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Template paths use `/` on every platform.
+fn slashed(path: &Path) -> String {
+    path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn reads_directory_as_template() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        dir.child(MANIFEST).write_str("description = \"CLI\"\n")?;
+        dir.child("src/{{ name }}.rs")
+            .write_str("fn {{ name }}() {}\n")?;
+        dir.child("docs/my notes.md")
+            .write_str("{### not a header ###}\n")?;
+        dir.child("empty").create_dir_all()?;
+        dir.child(".git/HEAD").write_str("ref: refs/heads/main\n")?;
+
+        assert_eq!(
+            read(dir.path())?,
+            r#"+++
+description = "CLI"
++++
+{### DELIMITERS sections="{#### ####}" ###}
+{#### DIR empty ####}
+{#### FILE "docs/my notes.md" ####}
+{### not a header ###}
+{#### FILE src/{{ name }}.rs ####}
+fn {{ name }}() {}
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_vars_become_var_sections() -> io::Result<()> {
+        let preamble = manifest_preamble(
+            "description = \"CLI\"\ntags = [\n  \"a\",\n  \"b, c\",\n]\n\n[vars.license]\nprompt = \"License\"\nchoices = [\n  \"MIT\",\n  \"Apache-2.0\",\n]\n[vars.author]\n",
+        )?;
+        assert_eq!(
+            preamble,
+            "+++\ndescription = \"CLI\"\ntags = [\n  \"a\",\n  \"b, c\",\n]\n+++\n{### VAR license prompt=License choices=MIT,Apache-2.0 ###}\n{### VAR author ###}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tells_templates_from_groups() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("group/a.tmplr").touch()?;
+        dir.child("group/b/README.md").touch()?;
+        dir.child("nested/c/tmplr.toml").touch()?;
+        dir.child("cli/Cargo.toml").touch()?;
+        dir.child("manifest/tmplr.toml").touch()?;
+        dir.child("empty").create_dir_all()?;
+
+        assert!(!is_template_dir(&dir.path().join("group")));
+        assert!(!is_template_dir(&dir.path().join("group/b")));
+        assert!(!is_template_dir(&dir.path().join("nested")));
+        assert!(!is_template_dir(&dir.path().join("cli")));
+        assert!(is_template_dir(&dir.path().join("manifest")));
+        assert!(!is_template_dir(&dir.path().join("empty")));
+        Ok(())
+    }
+}
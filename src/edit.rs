@@ -0,0 +1,462 @@
+use std::{io, path::Path};
+
+use regex::Regex;
+
+use crate::header::{Attributes, Eol};
+
+/// Line matcher used by `INSERT` anchors. Values written as `/.../` are
+/// treated as regular expressions, anything else as literal substring.
+#[derive(Debug)]
+pub enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn parse(value: &str) -> io::Result<Self> {
+        match value.strip_prefix('/').and_then(|v| v.strip_suffix('/')) {
+            Some(re) if value.len() > 1 => Regex::new(re)
+                .map(Pattern::Regex)
+                .map_err(|e| io::Error::other(format!("Invalid anchor regex {}: {}", value, e))),
+            _ => Ok(Pattern::Literal(value.into())),
+        }
+    }
+
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            Pattern::Literal(literal) => line.contains(literal.as_str()),
+            Pattern::Regex(re) => re.is_match(line),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Position {
+    After,
+    Before,
+}
+
+/// Where `INSERT` content goes: `after=`/`before=` a line, first or last
+/// matching one (`at=first|last`).
+#[derive(Debug)]
+pub struct Anchor {
+    pub position: Position,
+    pub pattern: Pattern,
+    pub last: bool,
+}
+
+impl Anchor {
+    pub fn from_attrs(attrs: &Attributes) -> io::Result<Self> {
+        let (position, value) = match (attrs.get("after"), attrs.get("before")) {
+            (Some(after), None) => (Position::After, after),
+            (None, Some(before)) => (Position::Before, before),
+            (Some(_), Some(_)) => {
+                return Err(io::Error::other(
+                    "INSERT takes either `after` or `before`, not both",
+                ));
+            }
+            (None, None) => {
+                return Err(io::Error::other(
+                    "INSERT section needs `after` or `before` attribute",
+                ));
+            }
+        };
+        let last = match attrs.get("at") {
+            None | Some("first") => false,
+            Some("last") => true,
+            Some(other) => {
+                return Err(io::Error::other(format!(
+                    "Invalid at attribute: {} (expected first or last)",
+                    other
+                )));
+            }
+        };
+        Ok(Anchor {
+            position,
+            pattern: Pattern::parse(value)?,
+            last,
+        })
+    }
+}
+
+/// Check used to keep `EXT`-like sections idempotent.
+pub fn already_contains(existing: &str, content: &str) -> bool {
+    existing.contains(content.trim())
+}
+
+/// Puts content in front of existing text, on its own lines.
+pub fn prepend(existing: &str, content: &str) -> String {
+    let mut result = String::with_capacity(existing.len() + content.len() + 2);
+    result.push_str(content);
+    if !content.is_empty() && !content.ends_with('\n') {
+        result.push_str(line_ending(existing));
+    }
+    result.push_str(existing);
+    result
+}
+
+/// Inserts content next to the anchor line, indenting every non-empty line
+/// with the anchor's indentation. Returns `None` if indented content is
+/// already present.
+pub fn insert(existing: &str, content: &str, anchor: &Anchor) -> io::Result<Option<String>> {
+    let eol = line_ending(existing);
+    let lines: Vec<&str> = existing.split_inclusive('\n').collect();
+    let mut matching = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| anchor.pattern.matches(line.trim_end_matches(['\r', '\n'])));
+    let found = match anchor.last {
+        true => matching.next_back(),
+        false => matching.next(),
+    };
+    let Some((idx, anchor_line)) = found else {
+        return Err(io::Error::other("Anchor line not found"));
+    };
+
+    let indent: String = anchor_line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let mut block = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            block.push_str(&indent);
+        }
+        block.push_str(line);
+    }
+    if already_contains(existing, &block) {
+        return Ok(None);
+    }
+    if !block.ends_with('\n') {
+        block.push_str(eol);
+    }
+
+    let split_at = match anchor.position {
+        Position::Before => idx,
+        Position::After => idx + 1,
+    };
+    let mut result = String::with_capacity(existing.len() + block.len() + 2);
+    for line in &lines[..split_at] {
+        result.push_str(line);
+    }
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push_str(eol);
+    }
+    result.push_str(&block);
+    for line in &lines[split_at..] {
+        result.push_str(line);
+    }
+    Ok(Some(result))
+}
+
+/// Line comment markers used for `BLOCK` markers, e.g. `#` or `<!-- -->`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommentSyntax {
+    pub open: String,
+    pub close: String,
+}
+
+impl CommentSyntax {
+    /// Picks comment syntax from file name, `#` for unknown files.
+    pub fn for_path(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let (open, close) = match ext.as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "go" | "java" | "kt" | "kts"
+            | "scala" | "swift" | "dart" | "zig" | "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx"
+            | "jsonc" | "proto" | "gradle" | "groovy" | "php" | "cue" | "scss" | "less" => {
+                ("//", "")
+            }
+            "sql" | "lua" | "hs" | "elm" | "ada" => ("--", ""),
+            "el" | "lisp" | "clj" | "cljs" | "scm" | "ini" | "asm" => (";", ""),
+            "tex" | "erl" => ("%", ""),
+            "vim" => ("\"", ""),
+            "html" | "htm" | "xml" | "svg" | "md" | "vue" => ("<!--", "-->"),
+            "css" => ("/*", "*/"),
+            _ if name == "vimrc" || name == ".vimrc" => ("\"", ""),
+            _ => ("#", ""),
+        };
+        CommentSyntax {
+            open: open.into(),
+            close: close.into(),
+        }
+    }
+
+    /// Parses `comment="//"` or `comment="<!-- -->"` attribute.
+    pub fn parse(value: &str) -> Self {
+        let mut parts = value.split_whitespace();
+        CommentSyntax {
+            open: parts.next().unwrap_or("#").into(),
+            close: parts.next().unwrap_or("").into(),
+        }
+    }
+
+    fn line(&self, text: &str) -> String {
+        match self.close.is_empty() {
+            true => format!("{} {}", self.open, text),
+            false => format!("{} {} {}", self.open, text, self.close),
+        }
+    }
+}
+
+/// Writes `content` between `>>> tmplr:ID` and `<<< tmplr:ID` comment
+/// lines, replacing what's there already. Block is appended when markers
+/// aren't found.
+pub fn replace_block(
+    existing: &str,
+    content: &str,
+    id: &str,
+    syntax: &CommentSyntax,
+) -> io::Result<String> {
+    let eol = line_ending(existing);
+    let begin = syntax.line(&format!(">>> tmplr:{}", id));
+    let end = syntax.line(&format!("<<< tmplr:{}", id));
+    let mut body = content.to_string();
+    if !body.is_empty() && !body.ends_with('\n') {
+        body.push_str(eol);
+    }
+
+    let lines: Vec<&str> = existing.split_inclusive('\n').collect();
+    let is_marker = |line: &str, marker: &str| line.trim() == marker;
+    let Some(begin_idx) = lines.iter().position(|l| is_marker(l, &begin)) else {
+        let mut result = existing.to_string();
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push_str(eol);
+        }
+        for part in [begin.as_str(), eol, body.as_str(), end.as_str(), eol] {
+            result.push_str(part);
+        }
+        return Ok(result);
+    };
+    let Some(end_idx) = lines[begin_idx..]
+        .iter()
+        .position(|l| is_marker(l, &end))
+        .map(|n| begin_idx + n)
+    else {
+        return Err(io::Error::other(format!(
+            "Block `{}` has no closing `{}` marker",
+            id, end
+        )));
+    };
+
+    let mut result = String::with_capacity(existing.len() + body.len());
+    for line in &lines[..=begin_idx] {
+        result.push_str(line);
+    }
+    result.push_str(&body);
+    for line in &lines[end_idx..] {
+        result.push_str(line);
+    }
+    Ok(result)
+}
+
+const KEEP_BEGIN: &str = "tmplr:keep begin";
+const KEEP_END: &str = "tmplr:keep end";
+
+/// Result of carrying `tmplr:keep` regions over; `dropped` lists regions of
+/// the old file which the new content doesn't have anymore.
+#[derive(Debug)]
+pub struct Kept {
+    pub content: String,
+    pub dropped: Vec<String>,
+}
+
+/// Copies contents of `tmplr:keep begin ID` ... `tmplr:keep end` regions
+/// from `old` file into the same regions of `new` content. Markers can use
+/// any comment syntax, only their text is matched.
+pub fn keep_regions(old: &str, new: &str) -> io::Result<Kept> {
+    let mut kept: Vec<(String, String)> = regions(old)?
+        .into_iter()
+        .map(|(id, range)| (id, old[range].to_string()))
+        .collect();
+
+    let mut content = String::with_capacity(new.len());
+    let mut cursor = 0;
+    for (id, range) in regions(new)? {
+        if let Some(idx) = kept.iter().position(|(k, _)| *k == id) {
+            content.push_str(&new[cursor..range.start]);
+            content.push_str(&kept.remove(idx).1);
+            cursor = range.end;
+        }
+    }
+    content.push_str(&new[cursor..]);
+    Ok(Kept {
+        content,
+        dropped: kept.into_iter().map(|(id, _)| id).collect(),
+    })
+}
+
+/// Ids and byte ranges of the lines between keep markers.
+fn regions(text: &str) -> io::Result<Vec<(String, std::ops::Range<usize>)>> {
+    let mut result = Vec::new();
+    let mut open: Option<(String, usize)> = None;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = pos;
+        pos += line.len();
+        if let Some(rest) = line.split_once(KEEP_BEGIN).map(|(_, rest)| rest) {
+            if let Some((id, _)) = &open {
+                return Err(io::Error::other(format!(
+                    "Nested tmplr:keep region in `{}`",
+                    id
+                )));
+            }
+            let id = rest
+                .split_whitespace()
+                .next()
+                .filter(|id| !["-->", "*/"].contains(id))
+                .ok_or_else(|| io::Error::other("tmplr:keep region without id"))?;
+            open = Some((id.into(), pos));
+        } else if line.contains(KEEP_END) {
+            match open.take() {
+                Some((id, start)) => result.push((id, start..line_start)),
+                None => return Err(io::Error::other("tmplr:keep end without begin")),
+            }
+        }
+    }
+    match open {
+        Some((id, _)) => Err(io::Error::other(format!(
+            "tmplr:keep region `{}` is not closed",
+            id
+        ))),
+        None => Ok(result),
+    }
+}
+
+fn line_ending(content: &str) -> &'static str {
+    Eol::detect(content).unwrap_or(Eol::Lf).as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(position: Position, pattern: &str, last: bool) -> Anchor {
+        Anchor {
+            position,
+            pattern: Pattern::parse(pattern).unwrap(),
+            last,
+        }
+    }
+
+    #[test]
+    fn prepends_on_own_line() {
+        assert_eq!(
+            prepend("fn main() {}\n", "use std::io;"),
+            "use std::io;\nfn main() {}\n"
+        );
+        assert_eq!(prepend("b\r\n", "a\r\n"), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn inserts_after_anchor_with_indent() -> io::Result<()> {
+        let existing = "fn routes() {\n    // tmplr:routes\n    home();\n}\n";
+        let got = insert(
+            existing,
+            "users();\n",
+            &anchor(Position::After, "// tmplr:routes", false),
+        )?;
+        assert_eq!(
+            got.as_deref(),
+            Some("fn routes() {\n    // tmplr:routes\n    users();\n    home();\n}\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_at_regex_match() -> io::Result<()> {
+        let existing = "mod a;\nmod b;\n\nfn main() {}";
+        let got = insert(
+            existing,
+            "mod c;",
+            &anchor(Position::After, "/^mod \\w+;$/", true),
+        )?;
+        assert_eq!(
+            got.as_deref(),
+            Some("mod a;\nmod b;\nmod c;\n\nfn main() {}")
+        );
+
+        let got = insert(
+            existing,
+            "// mods\n",
+            &anchor(Position::Before, "/^mod/", false),
+        )?;
+        assert_eq!(
+            got.as_deref(),
+            Some("// mods\nmod a;\nmod b;\n\nfn main() {}")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_after_last_line_without_newline() -> io::Result<()> {
+        let got = insert("a\nb", "c\n", &anchor(Position::After, "b", false))?;
+        assert_eq!(got.as_deref(), Some("a\nb\nc\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn insert_is_idempotent() -> io::Result<()> {
+        let existing = "{\n  // here\n  a: 1,\n  b: 2,\n}\n";
+        let got = insert(
+            existing,
+            "a: 1,\nb: 2,\n",
+            &anchor(Position::After, "here", false),
+        )?;
+        assert_eq!(got, None);
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_block_between_markers() -> io::Result<()> {
+        let syntax = CommentSyntax::for_path(Path::new(".gitignore"));
+        let first = replace_block("*.log\n", "target/\n", "rust", &syntax)?;
+        assert_eq!(
+            first,
+            "*.log\n# >>> tmplr:rust\ntarget/\n# <<< tmplr:rust\n"
+        );
+        let second = replace_block(&first, "target/\nCargo.lock", "rust", &syntax)?;
+        assert_eq!(
+            second,
+            "*.log\n# >>> tmplr:rust\ntarget/\nCargo.lock\n# <<< tmplr:rust\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn block_markers_follow_file_syntax() -> io::Result<()> {
+        let syntax = CommentSyntax::for_path(Path::new("index.html"));
+        let got = replace_block("", "<p>hi</p>\n", "body", &syntax)?;
+        assert_eq!(
+            got,
+            "<!-- >>> tmplr:body -->\n<p>hi</p>\n<!-- <<< tmplr:body -->\n"
+        );
+        assert!(replace_block("// >>> tmplr:x\n", "", "x", &CommentSyntax::parse("//")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_user_regions() -> io::Result<()> {
+        let old = "fn generated() {}\n// tmplr:keep begin helpers\nfn mine() {}\n// tmplr:keep end\n<!-- tmplr:keep begin gone -->\nx\n<!-- tmplr:keep end -->\n";
+        let new = "fn generated_v2() {}\n    // tmplr:keep begin helpers\n    // your code here\n    // tmplr:keep end\n";
+        let kept = keep_regions(old, new)?;
+        assert_eq!(
+            kept.content,
+            "fn generated_v2() {}\n    // tmplr:keep begin helpers\nfn mine() {}\n    // tmplr:keep end\n"
+        );
+        assert_eq!(kept.dropped, vec!["gone"]);
+        assert!(keep_regions("// tmplr:keep begin a\n", "").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_anchor_is_error() {
+        assert!(insert("a\n", "b\n", &anchor(Position::After, "x", false)).is_err());
+    }
+}
@@ -48,6 +48,20 @@ impl<T,E> UnwrapQuit<T, E> for Option<T> {
     }
 }
 
+/// Like `UnwrapQuit`, but reports the error itself instead of a fixed message.
+pub trait UnwrapReport<T> {
+    fn unwrap_or_report(self, err_code: i32) -> T;
+}
+impl<T, E: std::fmt::Display> UnwrapReport<T> for Result<T, E> {
+    #[inline]
+    fn unwrap_or_report(self, err_code: i32) -> T {
+        self.unwrap_or_else(|err| {
+            quit_with_error(err_code, &err.to_string());
+            unreachable!();
+        })
+    }
+}
+
 #[inline]
 pub fn quit_with_error(code: i32, err: &str) {
     eprintln!("Error: {}", err);
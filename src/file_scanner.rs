@@ -19,6 +19,7 @@ impl FileScanner {
             extension: None,
         }
     }
+    #[cfg(debug_assertions)]
     pub fn new_with_extension<P: AsRef<Path>>(root: P, extension: String) -> Self {
         Self {
             stack: vec![root.as_ref().to_path_buf()],
@@ -1,10 +1,10 @@
 use pathdiff::diff_paths;
-use std::fmt::Write;
 use std::io;
 use std::{fs, path::PathBuf};
 
 use crate::CreateArgs;
 use crate::error_handling::quit_with_error;
+use crate::header::{Attributes, Eol};
 use crate::{
     empty_dir_scanner, file_scanner,
     template::{self, EXTENSION, Node},
@@ -35,44 +35,36 @@ where
     T: Iterator<Item = io::Result<PathBuf>>,
     R: Iterator<Item = io::Result<PathBuf>>,
 {
-    let mut result = String::with_capacity(128 * 1024);
     let pathbuf = &args.path;
-
-    let open = template::OPEN;
-    let close = template::CLOSE;
+    let mut dir_paths: Vec<String> = Vec::new();
+    let mut file_entries: Vec<(String, String)> = Vec::new();
+    let mut file_attrs: Vec<Attributes> = Vec::new();
 
     for dir in dirs.flatten() {
         let dir_pathbuf = dir.clone();
         let relative = diff_paths(&dir_pathbuf, pathbuf)?;
         let path_str = relative.to_str()?;
         let new_node = create_dir_node(args, path_str);
-        if let Node::Dir(path) = new_node {
+        if let Node::Dir { path, .. } = new_node {
             let relative = diff_paths(&path, pathbuf)?;
-            let path_str = relative.to_str()?;
-            writeln!(result, "{open} DIR {path_str} {close}").unwrap()
+            dir_paths.push(relative.to_str()?.to_string());
         }
     }
 
     for file in files.flatten() {
         let file = file.clone();
         let file_path: &str = file.to_str()?;
-        let new_node = create_node(args, file_path);
-        match new_node {
-            Node::File { path, content } => {
-                        let relative = diff_paths(&path, pathbuf)?;
-                        let path_str = relative.to_str()?;
-                        writeln!(result, "{open} FILE {path_str} {close}").unwrap();
-                        result.push_str(&content);
-                        result.push('\n');
-                    }
-            Node::Dir(path) => {
-                        let relative = diff_paths(&path, pathbuf)?;
-                        let path_str = relative.to_str()?;
-                        writeln!(result, "{open} DIR {path_str} {close}").unwrap()
-                    }
-            Node::Ext { .. } => todo!("Implement after tmplr create --appending is added"),
-        }
+        let Node::File { path, content, attrs } = create_node(args, file_path) else {
+            unreachable!("create_node only builds FILE nodes");
+        };
+        let relative = diff_paths(&path, pathbuf)?;
+        // Body is kept byte-exact: content without final newline is
+        // directly followed by the next header
+        file_entries.push((relative.to_str()?.to_string(), content));
+        file_attrs.push(attrs);
     }
+    // Section markers are escalated when some file contains them
+    let result = template::write_template_with_attrs("", &dir_paths, &file_entries, &file_attrs);
     let mut filename: String = String::new();
     filename.push_str(args.name.as_str());
     filename.push('.');
@@ -87,13 +79,19 @@ where
 
 pub fn create_dir_node(args: &CreateArgs, path: &str) -> Node {
     if args.no_replace {
-        let pathbuf = template::validate_path_string(path).expect("Path error");
-        Node::Dir(pathbuf)
+        let pathbuf = template::validate_path_string(&args.path, path).expect("Path error");
+        Node::Dir {
+            path: pathbuf,
+            attrs: Attributes::default(),
+        }
     } else {
         let path = replace_word_bounded(path, &args.name, "{{ name }}");
-        let pathbuf = template::validate_path_string(path.as_str()).expect("Path error");
+        let pathbuf = template::validate_path_string(&args.path, path.as_str()).expect("Path error");
 
-        Node::Dir(pathbuf)
+        Node::Dir {
+            path: pathbuf,
+            attrs: Attributes::default(),
+        }
     }
 }
 pub fn create_node(args: &CreateArgs, path: &str) -> Node {
@@ -105,21 +103,48 @@ pub fn create_node(args: &CreateArgs, path: &str) -> Node {
         );
         unreachable!();
     };
+    let (content, attrs) = extract_encoding_attrs(content);
 
     if args.no_replace {
         let path_str = String::from(path);
         Node::File {
             path: path_str,
             content,
+            attrs,
         }
     } else {
         let content = replace_word_bounded(&content, &args.name, "{{ name }}");
         let path = replace_word_bounded(path, &args.name, "{{ name }}");
 
-        Node::File { path, content }
+        Node::File {
+            path,
+            content,
+            attrs,
+        }
     }
 }
 
+/// Template bodies are stored with LF line endings and without BOM,
+/// original ones are recorded as attributes to be restored by `make`.
+fn extract_encoding_attrs(content: String) -> (String, Attributes) {
+    let mut attrs = Attributes::default();
+    let content = match content.strip_prefix(template::BOM) {
+        Some(stripped) => {
+            attrs.insert("bom", "true");
+            stripped.to_string()
+        }
+        None => content,
+    };
+    let content = match Eol::detect(&content) {
+        Some(Eol::Crlf) => {
+            attrs.insert("eol", "crlf");
+            Eol::Lf.normalize(&content)
+        }
+        _ => content,
+    };
+    (content, attrs)
+}
+
 fn replace_word_bounded(input: &str, target: &str, replacement: &str) -> String {
     let mut output = String::with_capacity(input.len());
     let mut last_idx = 0;
@@ -0,0 +1,494 @@
+use std::{collections::HashMap, io, str::FromStr};
+
+use crate::{merge::OnConflict, render_template::render_with, template::Delimiters};
+
+/// Parsed contents of a `{### CMD arg... key=value... ###}` section header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub attrs: Attributes,
+}
+
+/// Line ending style selected with `eol=` attribute or `--eol` flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eol {
+    Lf,
+    Crlf,
+    Native,
+}
+
+impl Eol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Eol::Lf => "\n",
+            Eol::Crlf => "\r\n",
+            Eol::Native if cfg!(windows) => "\r\n",
+            Eol::Native => "\n",
+        }
+    }
+
+    /// Converts every line ending in `content` to this style.
+    pub fn normalize(&self, content: &str) -> String {
+        let lf = content.replace("\r\n", "\n");
+        match self.as_str() {
+            "\n" => lf,
+            eol => lf.replace('\n', eol),
+        }
+    }
+
+    /// Detects consistent line endings, `None` for mixed or single line content.
+    pub fn detect(content: &str) -> Option<Eol> {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        match (lf, crlf) {
+            (0, 0) => None,
+            (_, 0) => Some(Eol::Lf),
+            (0, _) => Some(Eol::Crlf),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Eol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lf" => Ok(Eol::Lf),
+            "crlf" => Ok(Eol::Crlf),
+            "native" => Ok(Eol::Native),
+            _ => Err(format!(
+                "Invalid eol value: {} (expected lf, crlf or native)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Eol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Eol::Lf => write!(f, "lf"),
+            Eol::Crlf => write!(f, "crlf"),
+            Eol::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Ordered `key=value` options attached to a section header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(k, _)| k.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn insert(&mut self, key: &str, value: &str) {
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.into(),
+            None => self.0.push((key.into(), value.into())),
+        }
+    }
+
+    /// Returns copy of attributes with variables expanded in every value.
+    pub fn render(&self, ctx: &HashMap<String, String>) -> Attributes {
+        let delims = self.delimiters();
+        Attributes(
+            self.0
+                .iter()
+                .map(|(k, v)| match k.as_str() {
+                    "delims" => (k.clone(), v.clone()),
+                    _ => (k.clone(), render_with(v, ctx, &delims)),
+                })
+                .collect(),
+        )
+    }
+
+    /// Variable delimiters from `delims="OPEN CLOSE"`, `{{ }}` by default.
+    /// Value is validated while parsing the template.
+    pub fn delimiters(&self) -> Delimiters {
+        self.get("delims")
+            .and_then(|d| Delimiters::parse(d).ok())
+            .unwrap_or_else(Delimiters::variables)
+    }
+
+    /// Unix permission bits from `mode=`, written in octal (e.g. `mode=755`).
+    pub fn mode(&self) -> io::Result<Option<u32>> {
+        let Some(mode) = self.get("mode") else {
+            return Ok(None);
+        };
+        u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|m| *m <= 0o7777)
+            .map(Some)
+            .ok_or_else(|| io::Error::other(format!("Invalid mode attribute: {}", mode)))
+    }
+
+    /// `trim=true` strips leading and trailing whitespace from the content.
+    pub fn trim(&self) -> io::Result<bool> {
+        Ok(self.flag("trim")?.unwrap_or(false))
+    }
+
+    /// `final_newline=true` ensures content ends with a newline,
+    /// `final_newline=false` removes trailing newlines. Content is kept as is
+    /// when not set.
+    pub fn final_newline(&self) -> io::Result<Option<bool>> {
+        self.flag("final_newline")
+    }
+
+    /// Line endings from `eol=lf|crlf|native`, kept as in template when not set.
+    pub fn eol(&self) -> io::Result<Option<Eol>> {
+        self.get("eol")
+            .map(|eol| eol.parse().map_err(io::Error::other))
+            .transpose()
+    }
+
+    /// `bom=true` prepends UTF-8 byte order mark to the file.
+    pub fn bom(&self) -> io::Result<bool> {
+        Ok(self.flag("bom")?.unwrap_or(false))
+    }
+
+    /// `once=true` writes the file only when it doesn't exist yet.
+    pub fn once(&self) -> io::Result<bool> {
+        Ok(self.flag("once")?.unwrap_or(false))
+    }
+
+    /// `secret=true` on `VAR` keeps its value out of the generation manifest.
+    pub fn secret(&self) -> io::Result<bool> {
+        Ok(self.flag("secret")?.unwrap_or(false))
+    }
+
+    /// Number of context lines PATCH hunks may ignore (`fuzz=N`), 2 by default.
+    pub fn fuzz(&self) -> io::Result<usize> {
+        match self.get("fuzz") {
+            None => Ok(2),
+            Some(fuzz) => fuzz
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::other(format!("Invalid fuzz attribute: {}", fuzz))),
+        }
+    }
+
+    /// How MERGE resolves differing values (`conflict=keep|override|error`),
+    /// existing values are kept by default.
+    pub fn on_conflict(&self) -> io::Result<OnConflict> {
+        self.get("conflict")
+            .map(|c| c.parse().map_err(io::Error::other))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    fn flag(&self, key: &str) -> io::Result<Option<bool>> {
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+        match value.trim().to_lowercase().as_str() {
+            "" | "1" | "true" | "yes" | "on" => Ok(Some(true)),
+            "0" | "false" | "no" | "off" => Ok(Some(false)),
+            _ => Err(io::Error::other(format!(
+                "Invalid {} attribute: {} (expected true or false)",
+                key, value
+            ))),
+        }
+    }
+
+    /// Evaluates `if=VAR` (or `if=!VAR`) against the context.
+    /// Sections without condition are always enabled.
+    pub fn condition(&self, ctx: &HashMap<String, String>) -> bool {
+        let Some(cond) = self.get("if") else {
+            return true;
+        };
+        let cond = cond.trim();
+        let (negate, var) = match cond.strip_prefix('!') {
+            Some(var) => (true, var.trim()),
+            None => (false, cond),
+        };
+        is_truthy(ctx.get(var).map(String::as_str)) != negate
+    }
+
+    /// Reports attributes which aren't in `known` for given section command.
+    pub fn warn_unknown(&self, cmd: &str, known: &[&str]) {
+        for key in self.keys().filter(|k| !known.contains(k)) {
+            if known.is_empty() {
+                eprintln!(
+                    "WARN: {} section doesn't take attributes, ignoring `{}`",
+                    cmd, key
+                );
+            } else {
+                eprintln!(
+                    "WARN: Unknown attribute `{}` in {} section (expected one of: {})",
+                    key,
+                    cmd,
+                    known.join(", ")
+                );
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Attributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs: Vec<String> = self
+            .0
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, quote(v)))
+            .collect();
+        write!(f, "{}", pairs.join(" "))
+    }
+}
+
+fn is_truthy(value: Option<&str>) -> bool {
+    match value.map(|v| v.trim().to_lowercase()) {
+        None => false,
+        Some(v) => !matches!(v.as_str(), "" | "0" | "false" | "no" | "off"),
+    }
+}
+
+/// Splits header contents into command, positional arguments and attributes.
+///
+/// Tokens are separated by whitespace unless quoted with `"` or `'`. An
+/// apostrophe only quotes at the start of a token or an attribute value, so
+/// `prompt=What's` is taken as is.
+/// Variables (`{{ ... }}` or custom `variables` delimiters) are kept intact
+/// even if they contain spaces.
+/// A token is an attribute when it starts with an identifier followed by `=`.
+pub fn parse_header(inner: &str, variables: &Delimiters) -> io::Result<Header> {
+    let mut tokens = tokenize(inner, variables)?.into_iter();
+    let Some(cmd) = tokens.next() else {
+        return Ok(Header::default());
+    };
+    let mut header = Header {
+        cmd: cmd.text,
+        ..Default::default()
+    };
+    for token in tokens {
+        match token.attribute() {
+            Some((key, value)) => header.attrs.insert(key, value),
+            None => header.args.push(token.text),
+        }
+    }
+    Ok(header)
+}
+
+/// Quotes value if it wouldn't survive header parsing as single argument.
+pub fn quote(value: &str) -> String {
+    let parsed = tokenize(value, &Delimiters::variables()).ok();
+    let survives = matches!(parsed.as_deref(), Some([token]) if token.text == value && token.attribute().is_none());
+    if survives {
+        return value.into();
+    }
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('"');
+    result
+}
+
+#[derive(Debug)]
+struct Token {
+    text: String,
+    // Byte offset of the first unquoted `=` in text
+    eq_pos: Option<usize>,
+}
+
+impl Token {
+    fn attribute(&self) -> Option<(&str, &str)> {
+        let pos = self.eq_pos?;
+        let key = &self.text[..pos];
+        let mut chars = key.chars();
+        let valid_start = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        (valid_start && valid_rest).then(|| (key, &self.text[pos + 1..]))
+    }
+}
+
+fn tokenize(input: &str, variables: &Delimiters) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut token = Token {
+            text: String::new(),
+            eq_pos: None,
+        };
+        while let Some((pos, c)) = chars.next() {
+            match c {
+                c if c.is_whitespace() => break,
+                '"' | '\''
+                    if c == '"'
+                        || token.text.is_empty()
+                        || token.eq_pos.is_some_and(|eq| eq + 1 == token.text.len()) =>
+                {
+                    let quote = c;
+                    loop {
+                        match chars.next() {
+                            None => {
+                                return Err(io::Error::other(format!(
+                                    "Unterminated quote in section header: {}",
+                                    input.trim()
+                                )));
+                            }
+                            Some((_, c)) if c == quote => break,
+                            Some((_, '\\')) if quote == '"' => match chars.next() {
+                                Some((_, escaped)) => token.text.push(escaped),
+                                None => token.text.push('\\'),
+                            },
+                            Some((_, c)) => token.text.push(c),
+                        }
+                    }
+                }
+                _ if input[pos..].starts_with(&variables.open) => {
+                    let rest = &input[pos + variables.open.len()..];
+                    let end = rest
+                        .find(&variables.close)
+                        .map(|e| pos + variables.open.len() + e + variables.close.len())
+                        .unwrap_or(input.len());
+                    token.text.push_str(&input[pos..end]);
+                    while chars.next_if(|(p, _)| *p < end).is_some() {}
+                }
+                '=' if token.eq_pos.is_none() => {
+                    token.eq_pos = Some(token.text.len());
+                    token.text.push(c);
+                }
+                c => token.text.push(c),
+            }
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str)]) -> Attributes {
+        Attributes(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_command_path_and_attributes() -> io::Result<()> {
+        let got = parse_header(
+            r#" FILE "path with spaces.txt" mode=644 if=ci eol=lf "#,
+            &Delimiters::variables(),
+        )?;
+        let expected = Header {
+            cmd: "FILE".into(),
+            args: vec!["path with spaces.txt".into()],
+            attrs: attrs(&[("mode", "644"), ("if", "ci"), ("eol", "lf")]),
+        };
+        assert_eq!(got, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_variables_with_spaces_together() -> io::Result<()> {
+        let got = parse_header(
+            "FILE d/e/{{ name }}.txt note=\"a {{ b }}\"",
+            &Delimiters::variables(),
+        )?;
+        assert_eq!(got.args, vec!["d/e/{{ name }}.txt".to_string()]);
+        assert_eq!(got.attrs.get("note"), Some("a {{ b }}"));
+        Ok(())
+    }
+
+    #[test]
+    fn quoted_equals_is_positional() -> io::Result<()> {
+        let got = parse_header(r#"FILE "a=b.txt" x='1 2'"#, &Delimiters::variables())?;
+        assert_eq!(got.args, vec!["a=b.txt".to_string()]);
+        assert_eq!(got.attrs.get("x"), Some("1 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn apostrophe_inside_value_is_literal() -> io::Result<()> {
+        let got = parse_header("VAR name prompt=What's", &Delimiters::variables())?;
+        assert_eq!(got.attrs.get("prompt"), Some("What's"));
+        let got = parse_header(
+            r#"VAR name prompt="What's your name""#,
+            &Delimiters::variables(),
+        )?;
+        assert_eq!(got.attrs.get("prompt"), Some("What's your name"));
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_quote_is_error() {
+        assert!(parse_header(r#"FILE "oops"#, &Delimiters::variables()).is_err());
+    }
+
+    #[test]
+    fn quote_round_trips() -> io::Result<()> {
+        for value in [
+            "plain.txt",
+            "with space.txt",
+            "a=b",
+            r#"q"uote"#,
+            "{{ name }}.txt",
+        ] {
+            let header = parse_header(&format!("FILE {}", quote(value)), &Delimiters::variables())?;
+            assert_eq!(header.args, vec![value.to_string()]);
+        }
+        assert_eq!(quote("{{ name }}.txt"), "{{ name }}.txt");
+        Ok(())
+    }
+
+    #[test]
+    fn custom_variable_delimiters_are_kept_together() -> io::Result<()> {
+        let got = parse_header("FILE <% name %>.txt", &Delimiters::new("<%", "%>")?)?;
+        assert_eq!(got.args, vec!["<% name %>.txt".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn eol_normalize_and_detect() {
+        assert_eq!(Eol::Crlf.normalize("a\nb\r\nc"), "a\r\nb\r\nc");
+        assert_eq!(Eol::Lf.normalize("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(Eol::detect("a\r\nb\r\n"), Some(Eol::Crlf));
+        assert_eq!(Eol::detect("a\nb\n"), Some(Eol::Lf));
+        assert_eq!(Eol::detect("a\nb\r\n"), None);
+        assert_eq!(Eol::detect("a"), None);
+        assert!(attrs(&[("eol", "cr")]).eol().is_err());
+    }
+
+    #[test]
+    fn typed_access() {
+        let ctx: HashMap<String, String> = [("ci".to_string(), "yes".to_string())].into();
+        assert_eq!(attrs(&[("mode", "755")]).mode().unwrap(), Some(0o755));
+        assert!(attrs(&[("mode", "9")]).mode().is_err());
+        assert!(attrs(&[("if", "ci")]).condition(&ctx));
+        assert!(!attrs(&[("if", "!ci")]).condition(&ctx));
+        assert!(!attrs(&[("if", "missing")]).condition(&ctx));
+        assert!(attrs(&[]).condition(&ctx));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, io, path::Path, process::Command};
+
+/// Prefix of environment variables carrying template variables to hooks.
+pub const ENV_PREFIX: &str = "TMPLR_";
+
+/// `name` becomes `TMPLR_NAME`, `crate-type` becomes `TMPLR_CRATE_TYPE`.
+pub fn env_name(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+    format!("{}{}", ENV_PREFIX, key)
+}
+
+/// Quotes `value` for the shell running hooks, so variables rendered into
+/// `RUN` body stay a single word. Values of safe characters are left as is.
+pub fn quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./,:=+@%".contains(c));
+    if safe {
+        return value.into();
+    }
+    quote_for_shell(value)
+}
+
+#[cfg(unix)]
+fn quote_for_shell(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(not(unix))]
+fn quote_for_shell(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Runs `RUN` section body with system shell in `dir`. Output is passed
+/// through on success and returned as part of the error otherwise.
+pub fn run(script: &str, variables: &HashMap<String, String>, dir: &Path) -> io::Result<()> {
+    let mut command = shell();
+    command.arg(script).current_dir(dir);
+    for (key, value) in variables {
+        command.env(env_name(key), value);
+    }
+    let output = command.output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Hook failed ({}):\n{}\n{}{}",
+            output.status,
+            script.trim(),
+            stdout,
+            stderr
+        )));
+    }
+    print!("{}", stdout);
+    eprint!("{}", stderr);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell() -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c");
+    command
+}
+
+#[cfg(not(unix))]
+fn shell() -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C");
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_names() {
+        assert_eq!(env_name("name"), "TMPLR_NAME");
+        assert_eq!(env_name("crate-type"), "TMPLR_CRATE_TYPE");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quotes_unsafe_values() {
+        assert_eq!(quote("my-app_2.0"), "my-app_2.0");
+        assert_eq!(quote("x; rm -rf ~"), "'x; rm -rf ~'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
     collections::HashMap,
     fmt::Write,
+    fs,
     path::{Path, PathBuf},
 };
 
 use crate::{
-    file_scanner,
-    template::{self, EXTENSION},
+    InfoArgs, dir_template,
+    meta::Meta,
+    template::{self, EXTENSION, read_template_string},
 };
 
 pub fn run_list() {
@@ -22,25 +24,65 @@ pub fn run_list() {
     }
 
     println!("Listing template dir: {}", templates_dir_str);
-    let template_tree: TemplateTree = templates.into();
+    let mut template_tree: TemplateTree = templates.into();
+    template_tree.annotate(&templates_dir);
     template_tree.print();
 }
 
+pub fn run_info(args: &InfoArgs) {
+    let meta = Meta::read(&args.template_path);
+    println!("Template: {}", args.template_path.to_string_lossy());
+    if meta.is_empty() {
+        println!(
+            "No metadata, add {{### META description=\"...\" tags=a,b ###}} at the top of the template"
+        );
+        return;
+    }
+    if let Some(description) = &meta.description {
+        println!("Description: {}", description);
+    }
+    if !meta.tags.is_empty() {
+        println!("Tags: {}", meta.tags.join(", "));
+    }
+    if let Some(version) = &meta.version {
+        println!("Version: {}", version);
+    }
+    if let Some(author) = &meta.author {
+        println!("Author: {}", author);
+    }
+    for (key, value) in &meta.extra {
+        println!("{}: {}", key, value);
+    }
+}
+
 pub fn fuzzy_select_template() -> Option<PathBuf> {
     let templates_dir = template::templates_dir();
     let templates = list_templates_relative(&templates_dir);
-    let templates_str: Vec<&str> = templates.iter().map(|p| p.to_str().unwrap()).collect();
+    // Description and tags are part of the item, so they can be searched too
+    let items: Vec<String> = templates
+        .iter()
+        .map(|p| {
+            let name = p.to_str().unwrap();
+            let summary = read_template_string(&templates_dir.join(p))
+                .ok()
+                .and_then(|content| Meta::parse(&content).summary());
+            match summary {
+                Some(summary) => format!("{}  {}", name, summary),
+                None => name.to_string(),
+            }
+        })
+        .collect();
 
     dialoguer::FuzzySelect::new()
         .with_prompt("template")
-        .items(&templates_str)
+        .items(&items)
         .interact_opt()
         .ok()?
         .map(|s| templates[s].clone())
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct TemplateFile(PathBuf);
+pub struct TemplateFile(PathBuf, Option<String>);
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Dirname(PathBuf);
@@ -79,6 +121,20 @@ impl TemplateTree {
         }
         TemplateTree(elements)
     }
+
+    /// Attaches metadata summaries to template files found under `dir`.
+    fn annotate(&mut self, dir: &Path) {
+        for node in self.0.iter_mut() {
+            match node {
+                TemplateNode::Dir(Dirname(name), tree) => tree.annotate(&dir.join(name)),
+                TemplateNode::File(TemplateFile(name, summary)) => {
+                    *summary = read_template_string(&dir.join(name))
+                        .ok()
+                        .and_then(|content| Meta::parse(&content).summary())
+                }
+            }
+        }
+    }
 }
 
 impl From<PathBuf> for TemplateTree {
@@ -97,7 +153,7 @@ impl From<PathBuf> for TemplateTree {
 
             if !saw_file {
                 saw_file = true;
-                node = Some(TemplateNode::File(TemplateFile(part)));
+                node = Some(TemplateNode::File(TemplateFile(part, None)));
                 continue;
             }
 
@@ -188,13 +244,41 @@ impl DepthPrint for TemplateFile {
                 .to_str()
                 .expect("Can't cast &OsStr to &str"),
         );
+        if let Some(summary) = &self.1 {
+            let _ = write!(result, ": {}", summary);
+        }
         result
     }
 }
 
+/// `.tmplr` files and directory templates found under `path`.
 pub fn list_templates_relative(path: &Path) -> Vec<PathBuf> {
-    file_scanner::FileScanner::new_with_extension(path, EXTENSION.into())
-        .flatten()
+    let mut templates = Vec::new();
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten().map(|e| e.path()) {
+            let hidden = entry
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if !entry.is_dir() {
+                if entry.extension().is_some_and(|ext| ext == EXTENSION) {
+                    templates.push(entry);
+                }
+            } else if dir_template::is_template_dir(&entry) {
+                templates.push(entry);
+            } else {
+                stack.push(entry);
+            }
+        }
+    }
+    templates
+        .into_iter()
         .map(|p| pathdiff::diff_paths(&p, path).unwrap_or(p))
         .collect()
 }
@@ -211,7 +295,7 @@ mod tests {
         TemplateNode::Dir(Dirname(PathBuf::from(s)), tt)
     }
     fn t_file(s: &str) -> TemplateNode {
-        TemplateNode::File(TemplateFile(PathBuf::from(s)))
+        TemplateNode::File(TemplateFile(PathBuf::from(s), None))
     }
     #[test]
     fn template_tree_from_pathbuf() -> Result<(), Box<dyn std::error::Error>> {
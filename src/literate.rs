@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use crate::{
+    header::{Attributes, parse_header},
+    template::{Delimiters, write_template_with_attrs},
+};
+
+pub const EXTENSIONS: &[&str] = &["md", "markdown"];
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Opening fence: marker (three or more backticks or tildes) and info string.
+fn fence(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    let marker_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(marker_char).len();
+    if len < 3 {
+        return None;
+    }
+    Some((&trimmed[..len], trimmed[len..].trim()))
+}
+
+/// `file=path` and other attributes of fenced block info string, e.g.
+/// ```` ```rust file=src/main.rs mode=644 ````.
+fn block_file(info: &str) -> Option<(String, Attributes)> {
+    let header = parse_header(&format!("CODE {}", info), &Delimiters::variables()).ok()?;
+    let path = header.attrs.get("file")?.to_string();
+    let mut attrs = Attributes::default();
+    for key in header.attrs.keys().filter(|key| *key != "file") {
+        attrs.insert(key, header.attrs.get(key).unwrap_or_default());
+    }
+    Some((path, attrs))
+}
+
+/// Turns Markdown into `.tmplr` text: fenced code blocks with `file=` become
+/// `FILE` sections, prose before the first of them becomes preamble and the
+/// rest of the prose is left out.
+pub fn to_template(text: &str) -> String {
+    let mut preamble = String::new();
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut attributes: Vec<Attributes> = Vec::new();
+    let mut lines = text.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        let Some((marker, info)) = fence(line) else {
+            if files.is_empty() {
+                preamble.push_str(line);
+            }
+            continue;
+        };
+        let mut content = String::new();
+        for line in lines.by_ref() {
+            if let Some((closing, "")) = fence(line)
+                && closing.starts_with(marker)
+            {
+                break;
+            }
+            content.push_str(line);
+        }
+        match block_file(info) {
+            Some((path, attrs)) => {
+                files.push((path, content));
+                attributes.push(attrs);
+            }
+            None if files.is_empty() => {
+                preamble.push_str(line);
+                preamble.push_str(&content);
+                preamble.push_str(marker);
+                preamble.push('\n');
+            }
+            None => (),
+        }
+    }
+    write_template_with_attrs(&preamble, &[], &files, &attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fenced_blocks_with_file_become_sections() {
+        let markdown = r#"# Rust CLI
+
+Start with the manifest:
+
+```toml file=Cargo.toml
+[package]
+name = "{{ name }}"
+```
+
+Example usage, not a file:
+
+```sh
+cargo run
+```
+
+````rust file="src/main.rs" mode=644
+fn main() {
+    // ```
+}
+````
+"#;
+        assert_eq!(
+            to_template(markdown),
+            r#"# Rust CLI
+
+Start with the manifest:
+
+{### FILE Cargo.toml ###}
+[package]
+name = "{{ name }}"
+{### FILE src/main.rs mode=644 ###}
+fn main() {
+    // ```
+}
+"#
+        );
+    }
+}
@@ -5,13 +5,29 @@ use std::{env::current_dir, str::FromStr};
 
 use crate::list_templates::fuzzy_select_template;
 
+mod conflict;
+mod cookiecutter;
+mod dir_template;
+mod edit;
 mod empty_dir_scanner;
 mod error_handling;
 mod file_scanner;
 mod gen_template;
+mod header;
+mod hooks;
 mod list_templates;
+mod literate;
+mod manifest;
+mod merge;
+mod meta;
+mod patch;
 mod render_template;
+mod require;
 mod template;
+mod three_way;
+mod transaction;
+mod txtar;
+mod update;
 
 #[derive(Debug)]
 struct CreateArgs {
@@ -20,22 +36,57 @@ struct CreateArgs {
     files: Option<Vec<PathBuf>>,
     no_replace: bool,
 }
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct MakeArgs {
     template_path: PathBuf,
     variables: HashMap<String, String>,
     dry_run: bool,
+    diff: bool,
+    eol: Option<header::Eol>,
+    yes: bool,
+    no_hooks: bool,
+    on_conflict: conflict::Policy,
+    output: Option<PathBuf>,
+    into_name: bool,
+}
+#[derive(Debug)]
+struct UpdateArgs {
+    template_path: Option<PathBuf>,
+    variables: HashMap<String, String>,
+    diff: bool,
+    yes: bool,
+    output: Option<PathBuf>,
 }
 #[derive(Debug)]
 struct EchoArgs {
     template_path: PathBuf,
 }
 #[derive(Debug)]
+struct InfoArgs {
+    template_path: PathBuf,
+}
+#[derive(Debug)]
+struct ConvertArgs {
+    input: PathBuf,
+    from: Option<txtar::Format>,
+    to: Option<txtar::Format>,
+}
+#[derive(Debug)]
+struct ImportArgs {
+    kind: String,
+    source: PathBuf,
+    output: Option<PathBuf>,
+}
+#[derive(Debug)]
 enum AppArgs {
     List,
+    Info(InfoArgs),
     Create(CreateArgs),
     Make(MakeArgs),
+    Update(UpdateArgs),
     Echo(EchoArgs),
+    Convert(ConvertArgs),
+    Import(ImportArgs),
     #[cfg(debug_assertions)]
     Debug,
 }
@@ -51,9 +102,13 @@ pub fn main() {
         #[cfg(debug_assertions)]
         AppArgs::Debug => run_debug(&args),
         AppArgs::Make(make_args) => render_template::make(&make_args),
+        AppArgs::Update(update_args) => update::run_update(&update_args),
         AppArgs::List => list_templates::run_list(),
+        AppArgs::Info(info_args) => list_templates::run_info(&info_args),
         AppArgs::Create(create_args) => gen_template::create_template(&create_args),
         AppArgs::Echo(echo_args) => render_template::echo(&echo_args),
+        AppArgs::Convert(convert_args) => txtar::run_convert(&convert_args),
+        AppArgs::Import(import_args) => cookiecutter::run_import(&import_args),
     }
 }
 
@@ -64,9 +119,10 @@ fn run_debug(_args: &AppArgs) {
     let mut ctx: HashMap<String, String> = HashMap::new();
     ctx.insert("project_name".into(), "[example_project]".into());
     for node in template.as_ref().unwrap() {
-        if let template::Node::File { path, content } = node {
+        if let template::Node::File { path, content, .. } = node {
             println!("{}", path);
-            println!("{}", render_template::render(content, &ctx));
+            let delims = template::Delimiters::variables();
+            println!("{}", render_template::render_with(content, &ctx, &delims));
         }
     }
 
@@ -97,6 +153,14 @@ fn parse_args() -> Result<AppArgs, pico_args::Error> {
         "dbg" => Ok(AppArgs::Debug {}),
         "make" => {
             let dry_run = pargs.contains(["-n", "--dry-run"]);
+            let diff = pargs.contains("--diff");
+            let yes = pargs.contains(["-y", "--yes"]);
+            let no_hooks = pargs.contains("--no-hooks");
+            let eol: Option<header::Eol> = pargs.opt_value_from_str("--eol")?;
+            let on_conflict: Option<conflict::Policy> =
+                pargs.opt_value_from_str("--on-conflict")?;
+            let output: Option<PathBuf> = pargs.opt_value_from_str(["-o", "--output"])?;
+            let into_name = pargs.contains("--into-name");
             let mut template_path: Option<PathBuf> = pargs.opt_free_from_str()?;
             let mut instance_name: Option<String> = pargs.opt_free_from_str()?;
 
@@ -131,10 +195,40 @@ fn parse_args() -> Result<AppArgs, pico_args::Error> {
                 template_path,
                 variables: ctx,
                 dry_run,
+                diff,
+                eol,
+                yes,
+                no_hooks,
+                on_conflict: on_conflict.unwrap_or_default(),
+                output,
+                into_name,
             });
 
             Ok(cmd)
         }
+        "update" => {
+            let diff = pargs.contains("--diff");
+            let yes = pargs.contains(["-y", "--yes"]);
+            let output: Option<PathBuf> = pargs.opt_value_from_str(["-o", "--output"])?;
+            let mut template_path: Option<PathBuf> = None;
+            let mut variables: HashMap<String, String> = HashMap::new();
+            for arg in pargs.finish() {
+                let str: String = arg.into_string().unwrap_or("".into());
+                match str.split_once("=") {
+                    Some((key, value)) => {
+                        variables.insert(key.into(), value.into());
+                    }
+                    None => template_path = Some(PathBuf::from(str)),
+                }
+            }
+            Ok(AppArgs::Update(UpdateArgs {
+                template_path,
+                variables,
+                diff,
+                yes,
+                output,
+            }))
+        }
         "echo" => {
             let template_path: PathBuf = pargs.opt_free_from_str()?.ok_or(pico_args::Error::MissingArgument)?;
             let cmd = AppArgs::Echo(EchoArgs {
@@ -175,7 +269,26 @@ fn parse_args() -> Result<AppArgs, pico_args::Error> {
                 }))
             }
         }
+        "convert" => {
+            let from: Option<txtar::Format> = pargs.opt_value_from_str("--from")?;
+            let to: Option<txtar::Format> = pargs.opt_value_from_str("--to")?;
+            let input: PathBuf = pargs.free_from_str()?;
+            Ok(AppArgs::Convert(ConvertArgs { input, from, to }))
+        }
+        "import" => Ok(AppArgs::Import(ImportArgs {
+            kind: pargs.free_from_str()?,
+            source: pargs.free_from_str()?,
+            output: pargs.opt_free_from_str()?,
+        })),
         "list" => Ok(AppArgs::List),
+        "info" => {
+            let template_path: Option<PathBuf> = pargs.opt_free_from_str()?;
+            let Some(template_path) = template_path.or_else(fuzzy_select_template) else {
+                print_help_and_exit(1);
+                unreachable!();
+            };
+            Ok(AppArgs::Info(InfoArgs { template_path }))
+        }
         _ => {
             print_help_and_exit(1);
             unreachable!();
@@ -204,6 +317,13 @@ fn run_interactive() -> Result<AppArgs, pico_args::Error> {
         template_path,
         variables: ctx,
         dry_run: false,
+        diff: false,
+        eol: None,
+        yes: false,
+        no_hooks: false,
+        on_conflict: conflict::Policy::default(),
+        output: None,
+        into_name: false,
     });
 
     Ok(cmd)
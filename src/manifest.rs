@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, value};
+
+use crate::{
+    error_handling::UnwrapReport,
+    template::{Node, is_in_library, templates_dir},
+    transaction::{Kind, Transaction},
+};
+
+/// Where `make` keeps its records, relative to the output directory.
+pub const DIR: &str = ".tmplr";
+
+/// Where `make` records what it generated, relative to the output directory.
+pub const PATH: &str = ".tmplr/manifest.toml";
+
+/// Where what the template generated is kept for `update` to merge
+/// against, mirroring the output directory.
+pub const BASE_DIR: &str = ".tmplr/base";
+
+/// Stands in for values of secret variables.
+pub const MASK: &str = "********";
+
+/// Variable names which are masked even without `secret=true`.
+const SECRET_WORDS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "private_key",
+    "credential",
+];
+
+/// Records of templates made into the output directory, one per template,
+/// so templates layered onto a project can each be updated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub tmplr_version: String,
+    pub templates: Vec<Record>,
+}
+
+/// Record of the last `make` of a template: with what variables, and the
+/// files it left behind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    /// Library name (e.g. `rust/cli`), or canonical path for other
+    /// templates.
+    pub name: String,
+    /// Canonical path of the template.
+    pub source: String,
+    pub hash: String,
+    pub variables: Vec<(String, String)>,
+    pub files: Vec<(String, String)>,
+}
+
+pub fn hash(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+/// Variables declared with `secret=true`.
+pub fn secrets(nodes: &[Node]) -> Vec<String> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Var { name, attrs } if attrs.secret().unwrap_or_report(1) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Where base of `path` is kept.
+pub fn base_path(path: &Path) -> PathBuf {
+    Path::new(BASE_DIR).join(path)
+}
+
+/// Values of secret variables, which are kept out of `.tmplr`.
+pub fn secret_values<'a>(
+    secrets: &[String],
+    variables: &'a HashMap<String, String>,
+) -> Vec<&'a str> {
+    variables
+        .iter()
+        .filter(|(name, value)| !value.is_empty() && is_secret(name, secrets))
+        .map(|(_, value)| value.as_str())
+        .collect()
+}
+
+fn is_secret(name: &str, secrets: &[String]) -> bool {
+    let lower = name.to_lowercase();
+    secrets.iter().any(|secret| secret == name)
+        || SECRET_WORDS.iter().any(|word| lower.contains(word))
+}
+
+/// Name template is recorded under: path inside templates library without
+/// extension, or canonical path when it comes from elsewhere, so unrelated
+/// templates with the same file name aren't taken for one another.
+pub fn template_name(path: &Path) -> String {
+    let canonical = path.canonicalize().unwrap_or(path.to_path_buf());
+    if !is_in_library(path) {
+        return canonical.to_string_lossy().into();
+    }
+    let library = templates_dir().canonicalize().unwrap_or(templates_dir());
+    let relative = canonical.strip_prefix(&library).unwrap_or(&canonical);
+    let relative = relative.with_extension("");
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl Record {
+    /// Record of files written by `transaction`, without the ones in
+    /// `.tmplr`.
+    /// `secrets` and variables named like a secret are masked.
+    pub fn new(
+        template_path: &Path,
+        template: &str,
+        secrets: &[String],
+        variables: &HashMap<String, String>,
+        transaction: &Transaction,
+    ) -> Self {
+        let mut variables: Vec<(String, String)> = variables
+            .iter()
+            .filter(|(name, _)| !name.starts_with('$'))
+            .map(|(name, value)| match is_secret(name, secrets) {
+                true => (name.clone(), MASK.into()),
+                false => (name.clone(), value.clone()),
+            })
+            .collect();
+        variables.sort();
+        let files = transaction
+            .touched()
+            .into_iter()
+            .filter(|path| !path.starts_with(DIR))
+            .filter(|path| transaction.kind(path) == Some(Kind::File))
+            .filter_map(|path| {
+                let content = transaction.read_to_string(&path).ok()?;
+                Some((slashed(&path), hash(content.as_bytes())))
+            })
+            .collect();
+        let source = template_path
+            .canonicalize()
+            .unwrap_or(template_path.to_path_buf());
+        Record {
+            name: template_name(template_path),
+            source: source.to_string_lossy().into(),
+            hash: hash(template.as_bytes()),
+            variables,
+            files,
+        }
+    }
+}
+
+impl Manifest {
+    /// Record of template `name`.
+    pub fn find(&self, name: &str) -> Option<&Record> {
+        self.templates.iter().find(|record| record.name == name)
+    }
+
+    /// Replaces record of the same template, or adds a new one.
+    pub fn record(&mut self, record: Record) {
+        match self.templates.iter_mut().find(|r| r.name == record.name) {
+            Some(existing) => *existing = record,
+            None => self.templates.push(record),
+        }
+    }
+
+    pub fn format(&self) -> String {
+        let mut doc = DocumentMut::new();
+        doc.decor_mut()
+            .set_prefix("# Written by `tmplr make`, don't edit by hand\n");
+        doc["tmplr_version"] = value(&self.tmplr_version);
+        let mut templates = ArrayOfTables::new();
+        for record in &self.templates {
+            let mut template = Table::new();
+            template["name"] = value(&record.name);
+            template["source"] = value(&record.source);
+            template["hash"] = value(&record.hash);
+            template["variables"] = Item::Table(string_table(&record.variables));
+            template["files"] = Item::Table(string_table(&record.files));
+            templates.push(template);
+        }
+        doc["template"] = Item::ArrayOfTables(templates);
+        doc.to_string()
+    }
+
+    /// Reads manifest written by `format`.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let doc: DocumentMut = text
+            .parse()
+            .map_err(|e| io::Error::other(format!("Invalid {}: {}", PATH, e)))?;
+        let templates = doc["template"]
+            .as_array_of_tables()
+            .map(|templates| {
+                templates
+                    .iter()
+                    .map(|template| Record {
+                        name: string(&template["name"]),
+                        source: string(&template["source"]),
+                        hash: string(&template["hash"]),
+                        variables: string_pairs(&template["variables"]),
+                        files: string_pairs(&template["files"]),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Manifest {
+            tmplr_version: string(&doc["tmplr_version"]),
+            templates,
+        })
+    }
+}
+
+fn slashed(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn string_table(pairs: &[(String, String)]) -> Table {
+    let mut table = Table::new();
+    for (key, string) in pairs {
+        table.insert(key, value(string));
+    }
+    table
+}
+
+fn string(item: &Item) -> String {
+    item.as_str().unwrap_or_default().into()
+}
+
+fn string_pairs(item: &Item) -> Vec<(String, String)> {
+    let Some(table) = item.as_table_like() else {
+        return Vec::new();
+    };
+    table
+        .iter()
+        .filter_map(|(key, item)| Some((key.to_string(), item.as_str()?.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_and_parses() -> io::Result<()> {
+        let cli = Record {
+            name: "rust/cli".into(),
+            source: "/home/me/templates/rust/cli.tmplr".into(),
+            hash: hash(b"template"),
+            variables: vec![
+                ("api_token".into(), MASK.into()),
+                ("name".into(), "say \"hi\"\n".into()),
+            ],
+            files: vec![("src/main rs.rs".into(), hash(b"fn main() {}\n"))],
+        };
+        let ci = Record {
+            name: "/home/me/ci.tmplr".into(),
+            source: "/home/me/ci.tmplr".into(),
+            ..Record::default()
+        };
+        let mut manifest = Manifest {
+            tmplr_version: "0.0.10".into(),
+            templates: vec![cli.clone(), ci],
+        };
+        let text = manifest.format();
+        assert!(text.contains("name = \"\"\"\nsay \"hi\"\n\"\"\"\n"));
+        assert!(text.contains("\"src/main rs.rs\" = \"sha256:"));
+        assert_eq!(Manifest::parse(&text)?, manifest);
+
+        manifest.record(Record {
+            hash: hash(b"new template"),
+            ..cli
+        });
+        assert_eq!(manifest.templates.len(), 2);
+        assert_eq!(
+            manifest.find("rust/cli").map(|r| r.hash.as_str()),
+            Some(hash(b"new template").as_str())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn masks_secrets() {
+        assert!(is_secret("GITHUB_TOKEN", &[]));
+        assert!(is_secret("db_password", &[]));
+        assert!(is_secret("license", &["license".into()]));
+        assert!(!is_secret("name", &[]));
+    }
+}
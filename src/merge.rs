@@ -0,0 +1,274 @@
+use std::{fmt, io, path::Path, str::FromStr};
+
+pub(crate) mod json;
+mod toml;
+mod yaml;
+
+/// Document format of `MERGE` target, guessed from extension unless given
+/// with `format=` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "json" | "jsonc" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(format!(
+                "Invalid format value: {} (expected json, toml or yaml)",
+                s
+            )),
+        }
+    }
+}
+
+/// What to do when fragment and existing document disagree on a value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    #[default]
+    Keep,
+    Override,
+    Error,
+}
+
+impl FromStr for OnConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "keep" => Ok(OnConflict::Keep),
+            "override" => Ok(OnConflict::Override),
+            "error" => Ok(OnConflict::Error),
+            _ => Err(format!(
+                "Invalid conflict value: {} (expected keep, override or error)",
+                s
+            )),
+        }
+    }
+}
+
+/// Merge result; `kept` and `overridden` list dotted paths of conflicting
+/// values.
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub content: String,
+    pub kept: Vec<String>,
+    pub overridden: Vec<String>,
+}
+
+/// Deep-merges `fragment` into `existing`. Maps are merged key by key,
+/// missing array items are appended, other differing values are conflicts.
+/// Existing text is edited in place, so its formatting and comments stay.
+pub fn merge(
+    existing: &str,
+    fragment: &str,
+    format: Format,
+    on_conflict: OnConflict,
+) -> io::Result<Merged> {
+    let mut merger = Merger {
+        on_conflict,
+        ..Merger::default()
+    };
+    if existing.trim().is_empty() {
+        return Ok(Merged {
+            content: fragment.into(),
+            ..Merged::default()
+        });
+    }
+    match format {
+        Format::Json => json::merge(existing, fragment, &mut merger)?,
+        Format::Toml => toml::merge(existing, fragment, &mut merger)?,
+        Format::Yaml => yaml::merge(existing, fragment, &mut merger)?,
+    }
+    Ok(Merged {
+        content: apply(existing, merger.edits),
+        kept: merger.kept,
+        overridden: merger.overridden,
+    })
+}
+
+/// Replacement of `start..end` of existing text; empty range inserts.
+#[derive(Debug)]
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+#[derive(Default)]
+struct Merger {
+    on_conflict: OnConflict,
+    edits: Vec<Edit>,
+    kept: Vec<String>,
+    overridden: Vec<String>,
+}
+
+impl Merger {
+    fn insert(&mut self, at: usize, text: String) {
+        self.edits.push(Edit {
+            start: at,
+            end: at,
+            text,
+        });
+    }
+
+    /// Resolves conflict at `path`, `replace` creates the overriding edit.
+    fn conflict(&mut self, path: &str, replace: impl FnOnce() -> Edit) -> io::Result<()> {
+        if self.resolve(path)? {
+            self.edits.push(replace());
+        }
+        Ok(())
+    }
+
+    /// Records conflict at `path`; true when the fragment value wins.
+    fn resolve(&mut self, path: &str) -> io::Result<bool> {
+        match self.on_conflict {
+            OnConflict::Keep => self.kept.push(path.into()),
+            OnConflict::Override => {
+                self.overridden.push(path.into());
+                return Ok(true);
+            }
+            OnConflict::Error => {
+                return Err(io::Error::other(format!(
+                    "Conflicting value for `{}` (use conflict=keep or conflict=override)",
+                    path
+                )));
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn apply(existing: &str, mut edits: Vec<Edit>) -> String {
+    // Insertions at the same place keep their order
+    edits.sort_by_key(|e| (e.start, e.end));
+    let mut result = String::with_capacity(existing.len());
+    let mut cursor = 0;
+    for edit in edits {
+        if edit.start < cursor {
+            continue;
+        }
+        result.push_str(&existing[cursor..edit.start]);
+        result.push_str(&edit.text);
+        cursor = edit.end;
+    }
+    result.push_str(&existing[cursor..]);
+    result
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    match parent.is_empty() {
+        true => key.into(),
+        false => format!("{}.{}", parent, key),
+    }
+}
+
+/// Whitespace and comments before `pos` on its line, i.e. its indentation.
+fn line_indent(src: &str, pos: usize) -> &str {
+    let start = src[..pos].rfind('\n').map(|n| n + 1).unwrap_or(0);
+    let line = &src[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Moves continuation lines of `text` from `from` to `to` indentation.
+fn reindent(text: &str, from: &str, to: &str) -> String {
+    let mut lines = text.split('\n');
+    let mut result = lines.next().unwrap_or("").to_string();
+    for line in lines {
+        result.push('\n');
+        match line.strip_prefix(from) {
+            Some(rest) if !line.trim().is_empty() => {
+                result.push_str(to);
+                result.push_str(rest);
+            }
+            _ => result.push_str(line),
+        }
+    }
+    result
+}
+
+/// Value text without whitespace and comments outside of strings, used to
+/// compare values regardless of formatting.
+fn canonical(text: &str, comment: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                result.push(c);
+                let mut escaped = false;
+                for (_, s) in chars.by_ref() {
+                    result.push(s);
+                    match s {
+                        '\\' if c == '"' && !escaped => escaped = true,
+                        _ if s == c && !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+            }
+            _ if text[i..].starts_with(comment) => {
+                while chars.peek().is_some_and(|(_, c)| *c != '\n') {
+                    chars.next();
+                }
+            }
+            _ if c.is_whitespace() => (),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "json",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_ignores_formatting() {
+        assert_eq!(
+            canonical("[ \"a b\",\n  'c' ] # note", "#"),
+            canonical("[\"a b\",'c']", "#")
+        );
+        assert_ne!(canonical("\"a b\"", "#"), canonical("\"ab\"", "#"));
+    }
+
+    #[test]
+    fn reindents_continuation_lines() {
+        assert_eq!(reindent("{\n    a\n  }", "  ", "    "), "{\n      a\n    }");
+    }
+
+    #[test]
+    fn empty_document_takes_fragment() -> io::Result<()> {
+        let merged = merge("", "a = 1\n", Format::Toml, OnConflict::Error)?;
+        assert_eq!(merged.content, "a = 1\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use std::io;
+
+use super::{Edit, Merger, canonical, join_path, line_indent, reindent};
+
+const COMMENT: &str = "//";
+
+/// JSON value with its byte range in the source. Comments (as in
+/// `tsconfig.json`) and trailing commas are tolerated.
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) value: Value,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+#[derive(Debug)]
+pub(crate) enum Value {
+    Object(Vec<Member>),
+    Array(Vec<Node>),
+    Scalar,
+}
+
+#[derive(Debug)]
+pub(crate) struct Member {
+    pub(crate) key: String,
+    pub(crate) key_start: usize,
+    pub(crate) key_end: usize,
+    pub(crate) value: Node,
+}
+
+/// Parses JSON document keeping byte ranges of its values.
+pub(crate) fn parse(src: &str) -> io::Result<Node> {
+    Parser::document(src)
+}
+
+/// Text of JSON string literal `quoted` with escapes resolved.
+pub(crate) fn unescape(quoted: &str) -> String {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|q| q.strip_suffix('"'))
+        .unwrap_or(quoted);
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('r') => result.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                    result.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(c) => result.push(c),
+                None => break,
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+pub(super) fn merge(existing: &str, fragment: &str, merger: &mut Merger) -> io::Result<()> {
+    let target = Parser::document(existing)?;
+    let source = Parser::document(fragment)?;
+    let docs = Docs { existing, fragment };
+    docs.merge_nodes(&target, &source, "", merger)
+}
+
+struct Docs<'a> {
+    existing: &'a str,
+    fragment: &'a str,
+}
+
+impl Docs<'_> {
+    fn merge_nodes(
+        &self,
+        target: &Node,
+        source: &Node,
+        path: &str,
+        m: &mut Merger,
+    ) -> io::Result<()> {
+        let (src, frag) = (self.existing, self.fragment);
+        match (&target.value, &source.value) {
+            (Value::Object(existing), Value::Object(new)) => {
+                let mut missing = Vec::new();
+                for member in new {
+                    match existing.iter().find(|e| e.key == member.key) {
+                        Some(e) => self.merge_nodes(
+                            &e.value,
+                            &member.value,
+                            &join_path(path, &member.key),
+                            m,
+                        )?,
+                        None => missing.push(member),
+                    }
+                }
+                let first = existing.first().map(|e| e.key_start);
+                let last = existing.last().map(|e| e.value.end);
+                self.append(
+                    target,
+                    first,
+                    last,
+                    &missing,
+                    |member, indent| {
+                        format!(
+                            "{}: {}",
+                            &frag[member.key_start..member.key_end],
+                            self.fragment_text(&member.value, member.key_start, indent)
+                        )
+                    },
+                    m,
+                );
+            }
+            (Value::Array(existing), Value::Array(new)) => {
+                let mut present: Vec<String> = existing
+                    .iter()
+                    .map(|item| canonical(&src[item.start..item.end], COMMENT))
+                    .collect();
+                let mut missing = Vec::new();
+                for item in new {
+                    let item_text = canonical(&frag[item.start..item.end], COMMENT);
+                    if !present.contains(&item_text) {
+                        present.push(item_text);
+                        missing.push(item);
+                    }
+                }
+                let first = existing.first().map(|e| e.start);
+                let last = existing.last().map(|e| e.end);
+                self.append(
+                    target,
+                    first,
+                    last,
+                    &missing,
+                    |item, indent| self.fragment_text(item, item.start, indent),
+                    m,
+                );
+            }
+            _ if canonical(&src[target.start..target.end], COMMENT)
+                == canonical(&frag[source.start..source.end], COMMENT) => {}
+            _ => {
+                let indent = line_indent(src, target.start);
+                let text = self.fragment_text(source, source.start, indent);
+                m.conflict(path, || Edit {
+                    start: target.start,
+                    end: target.end,
+                    text,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Source text of fragment node, indented like existing line with `indent`.
+    fn fragment_text(&self, node: &Node, line_pos: usize, indent: &str) -> String {
+        let frag = self.fragment;
+        reindent(
+            &frag[node.start..node.end],
+            line_indent(frag, line_pos),
+            indent,
+        )
+    }
+
+    /// Adds entries to the end of `target` object or array, following its
+    /// layout: one entry per line or all on one line.
+    fn append<T>(
+        &self,
+        target: &Node,
+        first: Option<usize>,
+        last: Option<usize>,
+        entries: &[&T],
+        format_entry: impl Fn(&T, &str) -> String,
+        m: &mut Merger,
+    ) {
+        if entries.is_empty() {
+            return;
+        }
+        let src = self.existing;
+        match (first, last) {
+            (Some(first), Some(last)) => {
+                let multiline = src[target.start..first].contains('\n');
+                let indent = line_indent(src, first);
+                let mut text = String::new();
+                for entry in entries {
+                    match multiline {
+                        true => text.push_str(&format!(",\n{}", indent)),
+                        false => text.push_str(", "),
+                    }
+                    text.push_str(&format_entry(entry, indent));
+                }
+                m.insert(last, text);
+            }
+            _ => {
+                let outer = line_indent(src, target.start);
+                let indent = format!("{}{}", outer, indent_unit(src));
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|entry| format_entry(entry, &indent))
+                    .collect();
+                let text = format!(
+                    "\n{}{}\n{}",
+                    indent,
+                    items.join(&format!(",\n{}", indent)),
+                    outer
+                );
+                let interior = target.start + 1..target.end - 1;
+                match src[interior.clone()].trim().is_empty() {
+                    true => m.edits.push(Edit {
+                        start: interior.start,
+                        end: interior.end,
+                        text,
+                    }),
+                    false => m.insert(interior.end, text),
+                }
+            }
+        }
+    }
+}
+
+/// Indentation of the first indented line, two spaces if there's none.
+fn indent_unit(src: &str) -> &str {
+    src.lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn document(src: &str) -> io::Result<Node> {
+        let mut parser = Parser { src, pos: 0 };
+        let node = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < src.len() {
+            return Err(parser.error("end of document"));
+        }
+        Ok(node)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self, expected: &str) -> io::Error {
+        let line = self.src[..self.pos.min(self.src.len())]
+            .matches('\n')
+            .count()
+            + 1;
+        io::Error::other(format!(
+            "Invalid JSON at line {}: expected {}",
+            line, expected
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                self.pos += trimmed.find("*/").map(|n| n + 2).unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn value(&mut self) -> io::Result<Node> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let value = match self.peek() {
+            Some(b'{') => self.object()?,
+            Some(b'[') => self.array()?,
+            Some(b'"') => {
+                self.string()?;
+                Value::Scalar
+            }
+            Some(_) => {
+                let rest = &self.src[self.pos..];
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || ",:]}/".contains(c))
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return Err(self.error("value"));
+                }
+                self.pos += len;
+                Value::Scalar
+            }
+            None => return Err(self.error("value")),
+        };
+        Ok(Node {
+            value,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn string(&mut self) -> io::Result<&str> {
+        let start = self.pos + 1;
+        let mut escaped = false;
+        for (i, c) in self.src[start..].char_indices() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => {
+                    self.pos = start + i + 1;
+                    return Ok(&self.src[start..start + i]);
+                }
+                _ => escaped = false,
+            }
+        }
+        Err(self.error("closing quote"))
+    }
+
+    fn object(&mut self) -> io::Result<Value> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b'}') {
+                self.pos += 1;
+                return Ok(Value::Object(members));
+            }
+            if self.peek() != Some(b'"') {
+                return Err(self.error("object key"));
+            }
+            let key_start = self.pos;
+            let key = self.string()?.to_string();
+            let key_end = self.pos;
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Err(self.error("`:`"));
+            }
+            self.pos += 1;
+            let value = self.value()?;
+            members.push(Member {
+                key,
+                key_start,
+                key_end,
+                value,
+            });
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => (),
+                _ => return Err(self.error("`,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> io::Result<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => (),
+                _ => return Err(self.error("`,` or `]`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Format, OnConflict, merge};
+
+    const PACKAGE: &str = r#"{
+  // npm package
+  "name": "app",
+  "scripts": {
+    "build": "tsc"
+  },
+  "keywords": ["a"],
+  "files": []
+}
+"#;
+
+    #[test]
+    fn merges_nested_objects_and_arrays() -> std::io::Result<()> {
+        let fragment = r#"{
+  "scripts": {
+    "build": "tsc",
+    "test": "vitest"
+  },
+  "keywords": ["a", "b"],
+  "files": ["dist"],
+  "license": "MIT"
+}"#;
+        let merged = merge(PACKAGE, fragment, Format::Json, OnConflict::Error)?;
+        assert_eq!(
+            merged.content,
+            r#"{
+  // npm package
+  "name": "app",
+  "scripts": {
+    "build": "tsc",
+    "test": "vitest"
+  },
+  "keywords": ["a", "b"],
+  "files": [
+    "dist"
+  ],
+  "license": "MIT"
+}
+"#
+        );
+        let again = merge(&merged.content, fragment, Format::Json, OnConflict::Error)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_conflicts() -> std::io::Result<()> {
+        let fragment = r#"{"name": "other"}"#;
+        assert!(merge(PACKAGE, fragment, Format::Json, OnConflict::Error).is_err());
+
+        let kept = merge(PACKAGE, fragment, Format::Json, OnConflict::Keep)?;
+        assert_eq!(kept.content, PACKAGE);
+        assert_eq!(kept.kept, vec!["name"]);
+
+        let overridden = merge(PACKAGE, fragment, Format::Json, OnConflict::Override)?;
+        assert!(overridden.content.contains(r#""name": "other","#));
+        Ok(())
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        let err = merge(
+            "{\n  \"a\": 1,\n  b\n}",
+            "{}",
+            Format::Json,
+            OnConflict::Keep,
+        );
+        assert!(err.unwrap_err().to_string().contains("line 3"));
+    }
+}
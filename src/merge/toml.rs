@@ -0,0 +1,302 @@
+use std::io;
+
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike, Value};
+
+use super::{Edit, Merger, canonical, join_path};
+
+const COMMENT: &str = "#";
+
+/// Merges with `toml_edit`, which keeps formatting and comments of the
+/// existing document. Tables, inline tables, dotted keys (`a.b = 1`) and
+/// sub-tables (`[a.b]`) are all the same to it, so they're merged alike.
+pub(super) fn merge(existing: &str, fragment: &str, merger: &mut Merger) -> io::Result<()> {
+    let mut target = parse(existing, "existing document")?;
+    let source = parse(fragment, "MERGE section")?;
+    merge_tables(target.as_table_mut(), source.as_table(), "", merger)?;
+    let content = target.to_string();
+    if content != existing {
+        merger.edits.push(Edit {
+            start: 0,
+            end: existing.len(),
+            text: content,
+        });
+    }
+    Ok(())
+}
+
+fn parse(text: &str, what: &str) -> io::Result<DocumentMut> {
+    text.parse()
+        .map_err(|err| io::Error::other(format!("Invalid TOML in {}: {}", what, err)))
+}
+
+fn merge_tables(
+    target: &mut dyn TableLike,
+    source: &dyn TableLike,
+    parent: &str,
+    m: &mut Merger,
+) -> io::Result<()> {
+    for (key, item) in source.iter() {
+        let path = join_path(parent, key);
+        let Some(existing) = target.get_mut(key) else {
+            let mut item = item.clone();
+            unposition(&mut item);
+            if let Item::Value(value) = &mut item {
+                value.decor_mut().clear();
+            }
+            // Comments above the key come along, spacing of inline tables doesn't
+            let mut key = source.key(key).expect("Key of iterated item").clone();
+            let blank = |decor: &toml_edit::Decor| {
+                decor
+                    .prefix()
+                    .and_then(|p| p.as_str())
+                    .is_none_or(|p| p.trim().is_empty())
+            };
+            if blank(key.leaf_decor()) {
+                key.fmt();
+            }
+            target.entry_format(&key).or_insert(item);
+            continue;
+        };
+        merge_items(existing, item, &path, m)?;
+    }
+    Ok(())
+}
+
+fn merge_items(existing: &mut Item, item: &Item, path: &str, m: &mut Merger) -> io::Result<()> {
+    if let (Item::Value(Value::InlineTable(target)), Some(source)) =
+        (&mut *existing, item.as_table_like())
+    {
+        let len = target.len();
+        merge_tables(target, source, path, m)?;
+        if target.len() != len {
+            target.fmt();
+        }
+        return Ok(());
+    }
+    if let (Some(target), Some(source)) = (existing.as_table_like_mut(), item.as_table_like()) {
+        return merge_tables(target, source, path, m);
+    }
+    match (existing, item) {
+        (Item::Value(Value::Array(target)), Item::Value(Value::Array(source))) => {
+            append_values(target, source);
+        }
+        (Item::ArrayOfTables(target), Item::ArrayOfTables(source)) => {
+            for table in source.iter() {
+                if !target.iter().any(|t| same_table(t, table)) {
+                    let mut item = Item::Table(table.clone());
+                    unposition(&mut item);
+                    if let Item::Table(table) = item {
+                        target.push(table);
+                    }
+                }
+            }
+        }
+        (Item::Value(target), Item::Value(source)) if same_value(target, source) => (),
+        (Item::Value(target), Item::Value(source)) => {
+            if m.resolve(path)? {
+                // New value takes place of the old one, comments around stay
+                let decor = target.decor().clone();
+                *target = source.clone();
+                *target.decor_mut() = decor;
+            }
+        }
+        (existing, item) => {
+            if m.resolve(path)? {
+                let mut item = item.clone();
+                unposition(&mut item);
+                *existing = item;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Appends items missing in `target`, formatted like its last item.
+fn append_values(target: &mut Array, source: &Array) {
+    for value in source.iter() {
+        if target.iter().any(|v| same_value(v, value)) {
+            continue;
+        }
+        let prefix = match target.iter().last() {
+            Some(last) => match last.decor().prefix().and_then(|p| p.as_str()) {
+                Some(prefix) if prefix.contains('\n') => prefix.to_string(),
+                _ => " ".into(),
+            },
+            None => String::new(),
+        };
+        let mut value = value.clone();
+        value.decor_mut().clear();
+        value.decor_mut().set_prefix(prefix);
+        target.push_formatted(value);
+    }
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    canonical(&a.to_string(), COMMENT) == canonical(&b.to_string(), COMMENT)
+}
+
+fn same_table(a: &Table, b: &Table) -> bool {
+    let values = |t: &Table| {
+        t.get_values()
+            .into_iter()
+            .map(|(keys, value)| {
+                let keys: Vec<&str> = keys.iter().map(|k| k.get()).collect();
+                (keys.join("."), canonical(&value.to_string(), COMMENT))
+            })
+            .collect::<Vec<_>>()
+    };
+    values(a) == values(b)
+}
+
+/// Lets tables taken from the fragment be placed after the ones around
+/// them, rather than at their position in the fragment, separated by a
+/// blank line.
+fn unposition(item: &mut Item) {
+    let tables: Vec<&mut Table> = match item {
+        Item::Table(table) => vec![table],
+        Item::ArrayOfTables(tables) => tables.iter_mut().collect(),
+        _ => return,
+    };
+    for table in tables {
+        table.set_position(None);
+        let prefix = table.decor().prefix().and_then(|p| p.as_str());
+        if !table.is_implicit()
+            && !table.is_dotted()
+            && !prefix.is_some_and(|p| p.starts_with('\n'))
+        {
+            let prefix = format!("\n{}", prefix.unwrap_or_default());
+            table.decor_mut().set_prefix(prefix);
+        }
+        for (_, item) in table.iter_mut() {
+            unposition(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Format, OnConflict, merge};
+
+    const CARGO: &str = r#"[package]
+name = "app" # our app
+version = "0.1.0"
+
+[dependencies]
+regex = "1"
+
+[workspace]
+members = [
+    "core",
+]
+"#;
+
+    #[test]
+    fn merges_tables_keys_and_arrays() -> std::io::Result<()> {
+        let fragment = r#"[dependencies]
+regex = "1"
+serde = { version = "1", features = ["derive"] }
+
+[workspace]
+members = ["core", "cli"]
+
+[dev-dependencies]
+assert_fs = "1"
+"#;
+        let merged = merge(CARGO, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(
+            merged.content,
+            r#"[package]
+name = "app" # our app
+version = "0.1.0"
+
+[dependencies]
+regex = "1"
+serde = { version = "1", features = ["derive"] }
+
+[workspace]
+members = [
+    "core",
+    "cli",
+]
+
+[dev-dependencies]
+assert_fs = "1"
+"#
+        );
+        let again = merge(&merged.content, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+
+    #[test]
+    fn merges_inline_tables() -> std::io::Result<()> {
+        let existing = "[dependencies]\nserde = { version = \"1\" }\n";
+        let fragment = "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n";
+        let merged = merge(existing, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(
+            merged.content,
+            "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_dotted_keys_and_sub_tables() -> std::io::Result<()> {
+        let existing = r#"[dependencies]
+# async runtime
+tokio.workspace = true
+
+[dependencies.clap]
+version = "4"
+"#;
+        let fragment = r#"[dependencies]
+tokio = { workspace = true }
+clap = { version = "4", features = ["derive"] }
+regex = "1"
+"#;
+        let merged = merge(existing, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(
+            merged.content,
+            r#"[dependencies]
+# async runtime
+tokio.workspace = true
+regex = "1"
+
+[dependencies.clap]
+version = "4"
+features = ["derive"]
+"#
+        );
+        let again = merge(&merged.content, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_conflicts() -> std::io::Result<()> {
+        let fragment = "[package]\nname = \"other\"\n";
+        let err = merge(CARGO, fragment, Format::Toml, OnConflict::Error).unwrap_err();
+        assert!(err.to_string().contains("package.name"));
+
+        let kept = merge(CARGO, fragment, Format::Toml, OnConflict::Keep)?;
+        assert_eq!(kept.content, CARGO);
+
+        let overridden = merge(CARGO, fragment, Format::Toml, OnConflict::Override)?;
+        assert!(
+            overridden
+                .content
+                .starts_with("[package]\nname = \"other\" # our app\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn array_tables_are_appended_once() -> std::io::Result<()> {
+        let fragment = "[[bin]]\nname = \"tool\"\n";
+        let merged = merge(CARGO, fragment, Format::Toml, OnConflict::Error)?;
+        assert!(merged.content.ends_with("]\n\n[[bin]]\nname = \"tool\"\n"));
+        let again = merge(&merged.content, fragment, Format::Toml, OnConflict::Error)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+}
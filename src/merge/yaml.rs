@@ -0,0 +1,713 @@
+use std::{io, ops::Range};
+
+use super::{Edit, Merger, canonical, join_path};
+
+const COMMENT: &str = "#";
+
+/// Non-blank, non-comment line of block-style YAML.
+#[derive(Debug)]
+struct Line {
+    start: usize,
+    /// After the newline
+    next: usize,
+    indent: usize,
+    dash: bool,
+}
+
+#[derive(Debug)]
+enum Block {
+    Map(Vec<Entry>),
+    Seq(Vec<Item>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    line: usize,
+    /// Inline value, without trailing comment
+    value: Option<Range<usize>>,
+    child: Option<Block>,
+    last_line: usize,
+}
+
+#[derive(Debug)]
+struct Item {
+    line: usize,
+    last_line: usize,
+}
+
+struct Doc<'a> {
+    text: &'a str,
+    lines: Vec<Line>,
+}
+
+pub(super) fn merge(existing: &str, fragment: &str, merger: &mut Merger) -> io::Result<()> {
+    let target = Doc::new(existing);
+    let source = Doc::new(fragment);
+    let (Some(target_root), Some(source_root)) = (target.root()?, source.root()?) else {
+        return Ok(());
+    };
+    let docs = Docs { target, source };
+    docs.merge_blocks(&target_root, &source_root, "", merger)
+}
+
+struct Docs<'a> {
+    target: Doc<'a>,
+    source: Doc<'a>,
+}
+
+impl Docs<'_> {
+    fn merge_blocks(
+        &self,
+        target: &Block,
+        source: &Block,
+        path: &str,
+        m: &mut Merger,
+    ) -> io::Result<()> {
+        let (t, s) = (&self.target, &self.source);
+        match (target, source) {
+            (Block::Map(existing), Block::Map(new)) => {
+                let mut missing = Vec::new();
+                for entry in new {
+                    match existing.iter().find(|e| e.key == entry.key) {
+                        Some(e) => self.merge_entries(e, entry, &join_path(path, &entry.key), m)?,
+                        None => missing.push((entry.line, entry.last_line)),
+                    }
+                }
+                if let (Some(first), Some(last)) = (existing.first(), existing.last()) {
+                    self.append(t.lines[first.line].indent, last.last_line, &missing, m);
+                }
+            }
+            (Block::Seq(existing), Block::Seq(new)) => {
+                let mut present: Vec<String> = existing
+                    .iter()
+                    .map(|i| canonical(t.span(i.line, i.last_line), COMMENT))
+                    .collect();
+                let mut missing = Vec::new();
+                for item in new {
+                    let text = canonical(s.span(item.line, item.last_line), COMMENT);
+                    if !present.contains(&text) {
+                        present.push(text);
+                        missing.push((item.line, item.last_line));
+                    }
+                }
+                if let (Some(first), Some(last)) = (existing.first(), existing.last()) {
+                    self.append(t.lines[first.line].indent, last.last_line, &missing, m);
+                }
+            }
+            _ => {
+                let text = s.text.to_string();
+                m.conflict(path, || Edit {
+                    start: 0,
+                    end: t.text.len(),
+                    text,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_entries(
+        &self,
+        target: &Entry,
+        source: &Entry,
+        path: &str,
+        m: &mut Merger,
+    ) -> io::Result<()> {
+        let (t, s) = (&self.target, &self.source);
+        match (t.flow_map(target), s.flow_map(source)) {
+            (Some(old), Some(new)) => {
+                let merged = merge_flow_maps(old, new, path, m)?;
+                if let (Some(value), true) = (&target.value, merged != old) {
+                    m.edits.push(Edit {
+                        start: value.start,
+                        end: value.end,
+                        text: merged,
+                    });
+                }
+                return Ok(());
+            }
+            (Some(_), None) | (None, Some(_))
+                if matches!(target.child, Some(Block::Map(_)))
+                    || matches!(source.child, Some(Block::Map(_))) =>
+            {
+                return Err(io::Error::other(format!(
+                    "Can't merge flow mapping `{{...}}` with block mapping at `{}`, write both the same way",
+                    path
+                )));
+            }
+            _ => (),
+        }
+        let flow = target.value.is_some() || source.value.is_some();
+        if let (true, Some(old), Some(new)) = (flow, t.sequence(target), s.sequence(source)) {
+            let mut missing: Vec<&str> = Vec::new();
+            for item in new {
+                if !old
+                    .iter()
+                    .chain(&missing)
+                    .any(|o| unquote(o) == unquote(item))
+                {
+                    missing.push(item);
+                }
+            }
+            self.append_items(target, &missing, m);
+            return Ok(());
+        }
+        match (&target.child, &source.child, &target.value, &source.value) {
+            (Some(old), Some(new), _, _) if same_kind(old, new) => {
+                self.merge_blocks(old, new, path, m)
+            }
+            (None, None, Some(old), Some(new))
+                if !t.text[old.clone()].contains('\n') && !s.text[new.clone()].contains('\n') =>
+            {
+                if unquote(&t.text[old.clone()]) == unquote(&s.text[new.clone()]) {
+                    return Ok(());
+                }
+                let text = s.text[new.clone()].to_string();
+                m.conflict(path, || Edit {
+                    start: old.start,
+                    end: old.end,
+                    text,
+                })
+            }
+            _ => {
+                let old = t.span(target.line, target.last_line);
+                let new = s.span(source.line, source.last_line);
+                if canonical(old, COMMENT) == canonical(new, COMMENT) {
+                    return Ok(());
+                }
+                let text = shift(
+                    new,
+                    s.lines[source.line].indent,
+                    t.lines[target.line].indent,
+                );
+                let start = t.lines[target.line].start;
+                let end = t.lines[target.last_line].next;
+                m.conflict(path, || Edit { start, end, text })
+            }
+        }
+    }
+
+    /// Adds scalar `items` to the sequence of `target`, inside the brackets
+    /// of a flow sequence or as `- item` lines of a block one.
+    fn append_items(&self, target: &Entry, items: &[&str], m: &mut Merger) {
+        if items.is_empty() {
+            return;
+        }
+        let t = &self.target;
+        if let Some(value) = &target.value {
+            m.edits.push(Edit {
+                start: value.start,
+                end: value.end,
+                text: extend_flow(&t.text[value.clone()], items),
+            });
+        } else if let Some(Block::Seq(existing)) = &target.child {
+            let (Some(first), Some(last)) = (existing.first(), existing.last()) else {
+                return;
+            };
+            let at = t.lines[last.last_line].next;
+            let indent = " ".repeat(t.lines[first.line].indent);
+            let mut text = String::new();
+            if !t.text[..at].ends_with('\n') {
+                text.push('\n');
+            }
+            for item in items {
+                text.push_str(&format!("{}- {}\n", indent, item));
+            }
+            m.insert(at, text);
+        }
+    }
+
+    /// Copies source lines after target line `after`, re-indented to `indent`.
+    fn append(&self, indent: usize, after: usize, ranges: &[(usize, usize)], m: &mut Merger) {
+        if ranges.is_empty() {
+            return;
+        }
+        let (t, s) = (&self.target, &self.source);
+        let at = t.lines[after].next;
+        let mut text = String::new();
+        if !t.text[..at].ends_with('\n') {
+            text.push('\n');
+        }
+        for (first, last) in ranges {
+            let block = shift(s.span(*first, *last), s.lines[*first].indent, indent);
+            text.push_str(&block);
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        m.insert(at, text);
+    }
+}
+
+fn same_kind(a: &Block, b: &Block) -> bool {
+    matches!(
+        (a, b),
+        (Block::Map(_), Block::Map(_)) | (Block::Seq(_), Block::Seq(_))
+    )
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+        .unwrap_or(value)
+}
+
+/// Moves every line of `text` from `from` to `to` spaces of indentation.
+fn shift(text: &str, from: usize, to: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            result.push_str(line);
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        result.push_str(&" ".repeat(to + spaces.saturating_sub(from)));
+        result.push_str(&line[spaces..]);
+    }
+    result
+}
+
+impl<'a> Doc<'a> {
+    fn new(text: &'a str) -> Doc<'a> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let next = text[start..]
+                .find('\n')
+                .map(|n| start + n + 1)
+                .unwrap_or(text.len());
+            let line = text[start..next].trim_end_matches(['\n', '\r']);
+            let content = line.trim_start_matches(' ');
+            let ignored = content.trim().is_empty()
+                || content.starts_with('#')
+                || content.starts_with("---")
+                || content.starts_with("...");
+            if !ignored {
+                lines.push(Line {
+                    start,
+                    next,
+                    indent: line.len() - content.len(),
+                    dash: content == "-" || content.starts_with("- "),
+                });
+            }
+            start = next;
+        }
+        Doc { text, lines }
+    }
+
+    /// Scalar items of the sequence in `entry`, written either as flow
+    /// sequence or as block one with single-line items.
+    fn sequence(&self, entry: &Entry) -> Option<Vec<&'a str>> {
+        match (&entry.value, &entry.child) {
+            (Some(value), None) => flow_items(&self.text[value.clone()]),
+            (None, Some(Block::Seq(items))) => items
+                .iter()
+                .map(|item| {
+                    if item.line != item.last_line {
+                        return None;
+                    }
+                    let value = strip_comment(self.content(item.line).strip_prefix('-')?).trim();
+                    let scalar = !value.is_empty()
+                        && !value.ends_with(':')
+                        && !value.contains(": ")
+                        && !value.starts_with(['[', '{', '|', '>']);
+                    scalar.then_some(value)
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    /// Single-line flow mapping `{a: 1}` written as value of `entry`.
+    fn flow_map(&self, entry: &Entry) -> Option<&'a str> {
+        let value = &self.text[entry.value.clone()?];
+        (value.starts_with('{') && value.ends_with('}') && !value.contains('\n')).then_some(value)
+    }
+
+    /// Text of lines `first..=last`.
+    fn span(&self, first: usize, last: usize) -> &'a str {
+        &self.text[self.lines[first].start..self.lines[last].next]
+    }
+
+    fn content(&self, line: usize) -> &'a str {
+        let l = &self.lines[line];
+        self.text[l.start + l.indent..l.next].trim_end_matches(['\n', '\r'])
+    }
+
+    fn root(&self) -> io::Result<Option<Block>> {
+        if self.lines.is_empty() {
+            return Ok(None);
+        }
+        let (block, _) = self.block(0)?;
+        Ok(Some(block))
+    }
+
+    /// Parses block starting at line `first`, returns it and next line index.
+    fn block(&self, first: usize) -> io::Result<(Block, usize)> {
+        let indent = self.lines[first].indent;
+        let dash = self.lines[first].dash;
+        let mut i = first;
+        let mut entries = Vec::new();
+        let mut items = Vec::new();
+        while i < self.lines.len() && self.lines[i].indent >= indent {
+            let line = &self.lines[i];
+            if line.indent > indent || line.dash != dash {
+                // Continuation of previous scalar
+                if line.indent == indent {
+                    break;
+                }
+                i += 1;
+                continue;
+            }
+            if dash {
+                let last = self.last_nested(i, indent);
+                items.push(Item {
+                    line: i,
+                    last_line: last,
+                });
+                i = last + 1;
+                continue;
+            }
+            let (entry, next) = self.entry(i, indent)?;
+            entries.push(entry);
+            i = next;
+        }
+        // Lines skipped as continuations belong to the last entry or item
+        if let Some(entry) = entries.last_mut() {
+            entry.last_line = entry.last_line.max(i - 1);
+        }
+        if let Some(item) = items.last_mut() {
+            item.last_line = item.last_line.max(i - 1);
+        }
+        Ok(match dash {
+            true => (Block::Seq(items), i),
+            false => (Block::Map(entries), i),
+        })
+    }
+
+    /// Last line indented deeper than `indent` following line `i`.
+    fn last_nested(&self, i: usize, indent: usize) -> usize {
+        let mut last = i;
+        while last + 1 < self.lines.len() && self.lines[last + 1].indent > indent {
+            last += 1;
+        }
+        last
+    }
+
+    fn entry(&self, i: usize, indent: usize) -> io::Result<(Entry, usize)> {
+        let content = self.content(i);
+        let line_number = self.text[..self.lines[i].start].matches('\n').count() + 1;
+        let colon = match content.chars().next() {
+            Some(q @ ('"' | '\'')) => content[1..]
+                .find(q)
+                .and_then(|end| content[end + 2..].starts_with(':').then_some(end + 2)),
+            _ => content
+                .find(": ")
+                .or_else(|| content.ends_with(':').then(|| content.len() - 1)),
+        };
+        let Some(colon) = colon else {
+            return Err(io::Error::other(format!(
+                "Invalid YAML at line {}: expected `key: value`",
+                line_number
+            )));
+        };
+        let key = unquote(&content[..colon]).to_string();
+        let value_start = self.lines[i].start + self.lines[i].indent + colon + 1;
+        let rest =
+            &self.text[value_start..self.lines[i].start + self.lines[i].indent + content.len()];
+        let trimmed = strip_comment(rest).trim();
+        let value_start = value_start + (rest.len() - rest.trim_start().len());
+
+        let mut entry = Entry {
+            key,
+            line: i,
+            value: None,
+            child: None,
+            last_line: i,
+        };
+        if trimmed.starts_with('|') || trimmed.starts_with('>') {
+            // Block scalar, kept as a whole
+            entry.last_line = self.last_nested(i, indent);
+            let end = self.lines[entry.last_line].next;
+            entry.value = Some(value_start..end);
+            let next = entry.last_line + 1;
+            return Ok((entry, next));
+        }
+        if !trimmed.is_empty() {
+            entry.value = Some(value_start..value_start + trimmed.len());
+            return Ok((entry, i + 1));
+        }
+        let child_start = i + 1;
+        let has_child = self
+            .lines
+            .get(child_start)
+            .is_some_and(|next| next.indent > indent || (next.indent == indent && next.dash));
+        if !has_child {
+            return Ok((entry, i + 1));
+        }
+        let (child, next) = self.block(child_start)?;
+        entry.child = Some(child);
+        entry.last_line = next - 1;
+        Ok((entry, next))
+    }
+}
+
+/// Items of single-line flow sequence `[a, b]`.
+fn flow_items(value: &str) -> Option<Vec<&str>> {
+    Some(split_flow(value.strip_prefix('[')?.strip_suffix(']')?))
+}
+
+/// `key: value` pairs of single-line flow mapping `{a: 1, b: 2}`.
+fn flow_pairs(value: &str) -> Option<Vec<(&str, &str)>> {
+    split_flow(value.strip_prefix('{')?.strip_suffix('}')?)
+        .into_iter()
+        .map(|pair| {
+            let colon = flow_colon(pair)?;
+            Some((pair[..colon].trim(), pair[colon + 1..].trim()))
+        })
+        .collect()
+}
+
+/// Position of `:` separating key from value, outside of quotes.
+fn flow_colon(pair: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in pair.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, ':') if pair[i + 1..].is_empty() || pair[i + 1..].starts_with(' ') => {
+                return Some(i);
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Flow sequence `text` with `items` added before its closing bracket.
+fn extend_flow(text: &str, items: &[&str]) -> String {
+    let inner = text[1..text.len() - 1].trim_end();
+    let separator = match inner.trim_start() {
+        "" => "",
+        i if i.ends_with(',') => " ",
+        _ => ", ",
+    };
+    format!(
+        "{}{}{}{}",
+        &text[..1 + inner.len()],
+        separator,
+        items.join(", "),
+        &text[1 + inner.len()..]
+    )
+}
+
+/// Deep-merges flow mapping `new` into `old` like block mappings: missing
+/// keys are added, nested flow mappings merged and sequences extended.
+fn merge_flow_maps(old: &str, new: &str, path: &str, m: &mut Merger) -> io::Result<String> {
+    let (Some(old_pairs), Some(new_pairs)) = (flow_pairs(old), flow_pairs(new)) else {
+        return Ok(old.into());
+    };
+    let mut pairs: Vec<(String, String)> = old_pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    for (key, value) in new_pairs {
+        let path = join_path(path, unquote(key));
+        let Some((_, existing)) = pairs.iter_mut().find(|(k, _)| unquote(k) == unquote(key)) else {
+            pairs.push((key.into(), value.into()));
+            continue;
+        };
+        if let (Some(_), Some(_)) = (flow_pairs(existing), flow_pairs(value)) {
+            *existing = merge_flow_maps(existing, value, &path, m)?;
+        } else if let (Some(items), Some(added)) = (flow_items(existing), flow_items(value)) {
+            let mut missing: Vec<&str> = Vec::new();
+            for item in added {
+                if !items
+                    .iter()
+                    .chain(&missing)
+                    .any(|i| unquote(i) == unquote(item))
+                {
+                    missing.push(item);
+                }
+            }
+            if !missing.is_empty() {
+                *existing = extend_flow(existing, &missing);
+            }
+        } else if unquote(existing) != unquote(value) && m.resolve(&path)? {
+            *existing = value.into();
+        }
+    }
+    if pairs
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .eq(old_pairs.iter().copied())
+    {
+        return Ok(old.into());
+    }
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("{}: {}", key, value))
+        .collect();
+    Ok(match old.starts_with("{ ") {
+        true => format!("{{ {} }}", pairs.join(", ")),
+        false => format!("{{{}}}", pairs.join(", ")),
+    })
+}
+
+/// Splits flow collection content on commas outside quotes and nesting.
+fn split_flow(inner: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut quote, mut depth, mut start) = (None, 0, 0);
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    items.push(inner[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// Drops ` # comment` outside of quotes.
+fn strip_comment(value: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut previous = ' ';
+    for (i, c) in value.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if previous.is_whitespace() => return &value[..i],
+            _ => (),
+        }
+        previous = c;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Format, OnConflict, merge};
+
+    const WORKFLOW: &str = "name: CI # main workflow
+on:
+  push:
+    branches:
+      - main
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - run: cargo test
+";
+
+    #[test]
+    fn merges_maps_and_sequences() -> std::io::Result<()> {
+        let fragment = "on:
+  push:
+    branches: [main, 'release']
+  pull_request:
+    branches:
+    - main
+jobs:
+  test:
+    steps:
+    - uses: actions/checkout@v4
+    - run: |
+        cargo fmt --check
+        cargo clippy
+  lint:
+    runs-on: ubuntu-latest
+";
+        let merged = merge(WORKFLOW, fragment, Format::Yaml, OnConflict::Keep)?;
+        assert!(merged.kept.is_empty());
+        assert_eq!(
+            merged.content,
+            "name: CI # main workflow
+on:
+  push:
+    branches:
+      - main
+      - 'release'
+  pull_request:
+    branches:
+    - main
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - run: cargo test
+      - run: |
+          cargo fmt --check
+          cargo clippy
+  lint:
+    runs-on: ubuntu-latest
+"
+        );
+        let again = merge(&merged.content, fragment, Format::Yaml, OnConflict::Keep)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+
+    #[test]
+    fn appends_to_flow_sequences() -> std::io::Result<()> {
+        let existing = "tags: [a, \"b, c\"]  # note\nempty: []\nlist:\n  - x\n";
+        let fragment = "tags: [a, d]\nempty:\n  - e\nlist: [x, 'y']\n";
+        let merged = merge(existing, fragment, Format::Yaml, OnConflict::Error)?;
+        assert_eq!(
+            merged.content,
+            "tags: [a, \"b, c\", d]  # note\nempty: [e]\nlist:\n  - x\n  - 'y'\n"
+        );
+        let again = merge(&merged.content, fragment, Format::Yaml, OnConflict::Error)?;
+        assert_eq!(again.content, merged.content);
+        Ok(())
+    }
+
+    #[test]
+    fn merges_flow_mappings() -> std::io::Result<()> {
+        let existing = "a: {x: 1, y: [p]}  # note\nb: { k: v }\n";
+        let fragment = "a: {z: 3, y: [q]}\nb: {k: w}\n";
+        let kept = merge(existing, fragment, Format::Yaml, OnConflict::Keep)?;
+        assert_eq!(
+            kept.content,
+            "a: {x: 1, y: [p, q], z: 3}  # note\nb: { k: v }\n"
+        );
+        assert_eq!(kept.kept, vec!["b.k"]);
+        let again = merge(&kept.content, fragment, Format::Yaml, OnConflict::Keep)?;
+        assert_eq!(again.content, kept.content);
+
+        let overridden = merge(existing, fragment, Format::Yaml, OnConflict::Override)?;
+        assert_eq!(
+            overridden.content,
+            "a: {x: 1, y: [p, q], z: 3}  # note\nb: { k: w }\n"
+        );
+        assert!(merge(existing, "a:\n  z: 3\n", Format::Yaml, OnConflict::Keep).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_scalar_conflicts() -> std::io::Result<()> {
+        let fragment = "name: \"Build\"\n";
+        assert!(merge(WORKFLOW, fragment, Format::Yaml, OnConflict::Error).is_err());
+        let overridden = merge(WORKFLOW, fragment, Format::Yaml, OnConflict::Override)?;
+        assert!(
+            overridden
+                .content
+                .starts_with("name: \"Build\" # main workflow\non:\n")
+        );
+        assert!(merge(WORKFLOW, "name: 'CI'\n", Format::Yaml, OnConflict::Error).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Value};
+
+use crate::{
+    header::parse_header,
+    template::{BOM, Delimiters, get_template_string_from_path},
+};
+
+/// Template description, read either from `{### META ... ###}` header at the
+/// top of the template or from TOML front matter between `+++` lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Meta {
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub extra: Vec<(String, String)>,
+}
+
+impl Meta {
+    /// Unreadable templates just have no metadata.
+    pub fn read(path: &Path) -> Meta {
+        get_template_string_from_path(path)
+            .map(|content| Meta::parse(&content))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(template: &str) -> Meta {
+        let template = template.strip_prefix(BOM).unwrap_or(template);
+        let mut meta = Meta::default();
+        let pairs = front_matter(template).or_else(|| meta_header(template));
+        for (key, value) in pairs.unwrap_or_default() {
+            meta.set(&key, value);
+        }
+        meta
+    }
+
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "description" => self.description = Some(value),
+            "version" => self.version = Some(value),
+            "author" => self.author = Some(value),
+            "tags" => {
+                self.tags = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect()
+            }
+            _ => self.extra.push((key.into(), value)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Meta::default()
+    }
+
+    /// One line shown next to template name: description and tags.
+    pub fn summary(&self) -> Option<String> {
+        let tags = match self.tags.is_empty() {
+            true => None,
+            false => Some(format!("[{}]", self.tags.join(", "))),
+        };
+        match (&self.description, tags) {
+            (Some(description), Some(tags)) => Some(format!("{} {}", description, tags)),
+            (Some(description), None) => Some(description.clone()),
+            (None, tags) => tags,
+        }
+    }
+}
+
+/// `META` section has to come before other sections.
+fn meta_header(template: &str) -> Option<Vec<(String, String)>> {
+    let sections = Delimiters::sections();
+    let start = template.find(&sections.open)? + sections.open.len();
+    let end = start + template[start..].find(&sections.close)?;
+    let header = parse_header(&template[start..end], &Delimiters::variables()).ok()?;
+    if !header.cmd.eq_ignore_ascii_case("META") {
+        return None;
+    }
+    Some(
+        header
+            .attrs
+            .keys()
+            .map(|key| (key.to_string(), header.attrs.get(key).unwrap_or("").into()))
+            .collect(),
+    )
+}
+
+/// Reads top-level keys of TOML between `+++` lines, tables are skipped.
+/// Invalid TOML means no metadata.
+fn front_matter(template: &str) -> Option<Vec<(String, String)>> {
+    let mut lines = template.lines();
+    if lines.next()?.trim() != "+++" {
+        return None;
+    }
+    let mut toml = String::new();
+    for line in lines.by_ref() {
+        if line.trim() == "+++" {
+            let document: DocumentMut = toml.parse().ok()?;
+            let pairs = document
+                .iter()
+                .filter_map(|(key, item)| Some((key.to_string(), toml_value(item.as_value()?))))
+                .collect();
+            return Some(pairs);
+        }
+        toml.push_str(line);
+        toml.push('\n');
+    }
+    None
+}
+
+/// TOML value as string, arrays are joined with commas.
+pub fn toml_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.value().clone(),
+        Value::Array(items) => items.iter().map(toml_value).collect::<Vec<_>>().join(","),
+        other => other.to_string().trim().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_meta_header() {
+        let meta = Meta::parse(
+            "Preamble\n{### META description=\"Rust CLI tool\" tags=rust,cli version=1.2 ###}\n{### FILE a ###}\n",
+        );
+        assert_eq!(meta.description.as_deref(), Some("Rust CLI tool"));
+        assert_eq!(meta.tags, vec!["rust", "cli"]);
+        assert_eq!(meta.version.as_deref(), Some("1.2"));
+        assert_eq!(meta.summary().as_deref(), Some("Rust CLI tool [rust, cli]"));
+    }
+
+    #[test]
+    fn reads_toml_front_matter() {
+        let meta = Meta::parse(
+            "+++\ndescription = \"Rust CLI tool\"\ntags = [\"rust\", \"cli\"]\nauthor = 'me'\nlicense = \"MIT\"\n+++\n{### FILE a ###}\n",
+        );
+        assert_eq!(meta.description.as_deref(), Some("Rust CLI tool"));
+        assert_eq!(meta.tags, vec!["rust", "cli"]);
+        assert_eq!(meta.author.as_deref(), Some("me"));
+        assert_eq!(meta.extra, vec![("license".into(), "MIT".into())]);
+    }
+
+    #[test]
+    fn reads_multi_line_front_matter() {
+        let meta = Meta::parse(
+            "+++\ndescription = \"Small, fast CLI\" # comment\ntags = [\n  \"rust\",\n  \"cli\", # binary\n]\nversion = 2\n+++\n",
+        );
+        assert_eq!(meta.description.as_deref(), Some("Small, fast CLI"));
+        assert_eq!(meta.tags, vec!["rust", "cli"]);
+        assert_eq!(meta.version.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn no_meta() {
+        assert!(Meta::parse("{### FILE a ###}\n{### META description=late ###}\n").is_empty());
+    }
+}
@@ -0,0 +1,529 @@
+use std::{fmt::Write, io};
+
+use crate::header::Eol;
+
+/// Single `@@ ... @@` block of a unified diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// Outcome of applying a patch; rejected hunks weren't applied, hunks
+/// whose `+` side is already in the file are counted as `already_applied`.
+#[derive(Debug)]
+pub struct Applied {
+    pub content: String,
+    pub applied: usize,
+    pub already_applied: usize,
+    pub rejected: Vec<Hunk>,
+}
+
+/// Parses hunks of a unified diff. File headers (`---`, `+++`, `diff ...`)
+/// are skipped, since PATCH section names its target itself. Line counts in
+/// hunk headers are ignored, as rendered variables may change them.
+pub fn parse(diff: &str) -> io::Result<Vec<Hunk>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut lines = diff.lines().peekable();
+    while let Some(line) = lines.next() {
+        // `--- a/x` followed by `+++ b/x` starts the next file, anything else
+        // starting with `-` is a removed line
+        let file_header =
+            line.starts_with("--- ") && lines.peek().is_some_and(|next| next.starts_with("+++ "));
+        if file_header {
+            lines.next();
+            continue;
+        }
+        if line.starts_with("@@") {
+            hunks.push(Hunk {
+                header: line.into(),
+                old_start: parse_old_start(line)?,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // Preamble of the diff (headers, description)
+            continue;
+        };
+        if line.starts_with("diff ") || line.starts_with("index ") {
+            continue;
+        }
+        match line.split_at_checked(1) {
+            Some((" ", rest)) => hunk.lines.push(HunkLine::Context(rest.into())),
+            Some(("-", rest)) => hunk.lines.push(HunkLine::Remove(rest.into())),
+            Some(("+", rest)) => hunk.lines.push(HunkLine::Add(rest.into())),
+            Some(("\\", _)) => (), // "\ No newline at end of file"
+            // Editors tend to strip lone space of empty context lines
+            None => hunk.lines.push(HunkLine::Context(String::new())),
+            Some(_) => {
+                return Err(io::Error::other(format!(
+                    "Malformed line in patch hunk {}: {}",
+                    hunk.header, line
+                )));
+            }
+        }
+    }
+    if hunks.is_empty() {
+        return Err(io::Error::other("Patch doesn't contain any hunks"));
+    }
+    Ok(hunks)
+}
+
+fn parse_old_start(header: &str) -> io::Result<usize> {
+    header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('-'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| io::Error::other(format!("Malformed hunk header: {}", header)))
+}
+
+/// Applies hunks to `existing`. Each hunk is looked up near its original
+/// position (shifted by previous hunks), first exactly, then ignoring
+/// whitespace, then with up to `fuzz` context lines dropped from its edges.
+/// Hunks which don't apply, but whose result is found in the file, were
+/// applied before and are skipped.
+pub fn apply(existing: &str, hunks: &[Hunk], fuzz: usize) -> Applied {
+    let eol = Eol::detect(existing).unwrap_or(Eol::Lf).as_str();
+    let had_final_newline = existing.is_empty() || existing.ends_with('\n');
+    let mut lines: Vec<String> = existing
+        .lines()
+        .map(|l| l.trim_end_matches('\r').to_string())
+        .collect();
+    let mut offset: isize = 0;
+    let mut applied = 0;
+    let mut already_applied = 0;
+    let mut rejected = Vec::new();
+
+    for hunk in hunks {
+        // Fuzzy match is only tried when the hunk isn't applied already
+        let found = match locate(&lines, hunk, offset, 0) {
+            Some(found) => Some(found),
+            None if locate(&lines, &reversed(hunk), offset, 0).is_some() => {
+                already_applied += 1;
+                continue;
+            }
+            None => locate(&lines, hunk, offset, fuzz),
+        };
+        match found {
+            Some((pos, skip_front, skip_back)) => {
+                let body = &hunk.lines[skip_front..hunk.lines.len() - skip_back];
+                let mut file_idx = pos;
+                let mut new_lines = Vec::with_capacity(body.len());
+                for line in body {
+                    match line {
+                        // Keep file's own version of fuzzy-matched context
+                        HunkLine::Context(_) => {
+                            new_lines.push(lines[file_idx].clone());
+                            file_idx += 1;
+                        }
+                        HunkLine::Remove(_) => file_idx += 1,
+                        HunkLine::Add(text) => new_lines.push(text.clone()),
+                    }
+                }
+                let old_len = file_idx - pos;
+                let new_len = new_lines.len();
+                lines.splice(pos..file_idx, new_lines);
+                offset =
+                    pos as isize - (hunk.old_start + skip_front) as isize + 1 + new_len as isize
+                        - old_len as isize;
+                applied += 1;
+            }
+            None => rejected.push(hunk.clone()),
+        }
+    }
+
+    let mut content = lines.join(eol);
+    if had_final_newline && !lines.is_empty() {
+        content.push_str(eol);
+    }
+    Applied {
+        content,
+        applied,
+        already_applied,
+        rejected,
+    }
+}
+
+/// Hunk undoing `hunk`, its old side is what `hunk` leaves behind.
+fn reversed(hunk: &Hunk) -> Hunk {
+    let lines = hunk
+        .lines
+        .iter()
+        .map(|line| match line {
+            HunkLine::Context(text) => HunkLine::Context(text.clone()),
+            HunkLine::Remove(text) => HunkLine::Add(text.clone()),
+            HunkLine::Add(text) => HunkLine::Remove(text.clone()),
+        })
+        .collect();
+    Hunk {
+        lines,
+        ..hunk.clone()
+    }
+}
+
+/// Finds hunk position; returns start line and number of context lines
+/// dropped from the front and the back.
+fn locate(
+    lines: &[String],
+    hunk: &Hunk,
+    offset: isize,
+    fuzz: usize,
+) -> Option<(usize, usize, usize)> {
+    let leading = hunk
+        .lines
+        .iter()
+        .take_while(|l| matches!(l, HunkLine::Context(_)))
+        .count();
+    let trailing = hunk
+        .lines
+        .iter()
+        .rev()
+        .take_while(|l| matches!(l, HunkLine::Context(_)))
+        .count();
+
+    for level in 0..=fuzz {
+        let skip_front = level.min(leading);
+        let skip_back = level.min(trailing);
+        if level > 0 && skip_front + skip_back == 0 {
+            break;
+        }
+        let body = &hunk.lines[skip_front..hunk.lines.len() - skip_back];
+        let old: Vec<&str> = body
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(t) | HunkLine::Remove(t) => Some(t.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect();
+        if old.len() > lines.len() {
+            continue;
+        }
+        let expected = (hunk.old_start as isize - 1 + offset + skip_front as isize)
+            .clamp(0, (lines.len() - old.len()) as isize) as usize;
+
+        for exact in [true, false] {
+            let matches_at = |pos: usize| {
+                old.iter().zip(&lines[pos..]).all(|(a, b)| match exact {
+                    true => *a == b,
+                    false => a.split_whitespace().eq(b.split_whitespace()),
+                })
+            };
+            // Search outwards from expected position
+            let max = lines.len() - old.len();
+            for distance in 0..=max {
+                let candidates = [expected.checked_sub(distance), Some(expected + distance)];
+                for pos in candidates.into_iter().flatten() {
+                    if pos <= max && matches_at(pos) {
+                        return Some((pos, skip_front, skip_back));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Formats rejected hunks as unified diff, for `.rej` files.
+pub fn format_rejects(path: &str, hunks: &[Hunk]) -> String {
+    format_diff(path, path, hunks)
+}
+
+pub fn format_diff(old_name: &str, new_name: &str, hunks: &[Hunk]) -> String {
+    let mut result = String::new();
+    let _ = writeln!(result, "--- {}", old_name);
+    let _ = writeln!(result, "+++ {}", new_name);
+    for hunk in hunks {
+        let _ = writeln!(result, "{}", hunk.header);
+        for line in &hunk.lines {
+            let _ = match line {
+                HunkLine::Context(t) => writeln!(result, " {}", t),
+                HunkLine::Remove(t) => writeln!(result, "-{}", t),
+                HunkLine::Add(t) => writeln!(result, "+{}", t),
+            };
+        }
+    }
+    result
+}
+
+/// Line diff of `old` and `new` as hunks with `context` unchanged lines
+/// around changes, based on their longest common subsequence.
+pub fn diff(old: &str, new: &str, context: usize) -> Vec<Hunk> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut script: Vec<HunkLine> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (a, b) in common_lines(&old, &new)
+        .into_iter()
+        .chain([(old.len(), new.len())])
+    {
+        script.extend(
+            old[i..a]
+                .iter()
+                .map(|line| HunkLine::Remove(line.to_string())),
+        );
+        script.extend(new[j..b].iter().map(|line| HunkLine::Add(line.to_string())));
+        if a < old.len() {
+            script.push(HunkLine::Context(old[a].into()));
+        }
+        (i, j) = (a + 1, b + 1);
+    }
+    group_hunks(script, context)
+}
+
+/// Index pairs of lines in `old` and `new` which are kept, the longest
+/// common subsequence of both.
+pub fn common_lines(old: &[&str], new: &[&str]) -> Vec<(usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    middle_lines(old_mid, new_mid, (prefix, prefix), &mut pairs);
+    pairs.extend((0..suffix).map(|k| (old.len() - suffix + k, new.len() - suffix + k)));
+    pairs
+}
+
+/// Common subsequence of lines in the middle, found by splitting `old` in
+/// half where it meets the best split of `new` (Hirschberg), so only two
+/// rows of lengths are kept instead of the whole table.
+fn middle_lines(
+    old: &[&str],
+    new: &[&str],
+    offset: (usize, usize),
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(j) = new.iter().position(|line| *line == old[0]) {
+            pairs.push((offset.0, offset.1 + j));
+        }
+        return;
+    }
+    let mid = old.len() / 2;
+    let forward = lcs_lengths(old[..mid].iter(), new.iter());
+    let backward = lcs_lengths(old[mid..].iter().rev(), new.iter().rev());
+    let split = (0..=new.len())
+        .max_by_key(|&k| (forward[k] + backward[new.len() - k], usize::MAX - k))
+        .unwrap_or(0);
+    middle_lines(&old[..mid], &new[..split], offset, pairs);
+    middle_lines(
+        &old[mid..],
+        &new[split..],
+        (offset.0 + mid, offset.1 + split),
+        pairs,
+    );
+}
+
+/// Lengths of the longest common subsequence of `old` and every prefix of
+/// `new`.
+fn lcs_lengths<'a>(
+    old: impl Iterator<Item = &'a &'a str>,
+    new: impl Iterator<Item = &'a &'a str> + Clone,
+) -> Vec<usize> {
+    let mut previous = vec![0; new.clone().count() + 1];
+    let mut current = previous.clone();
+    for line in old {
+        for (j, other) in new.clone().enumerate() {
+            current[j + 1] = match line == other {
+                true => previous[j] + 1,
+                false => previous[j + 1].max(current[j]),
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous
+}
+
+/// Splits edit script into hunks, merging changes closer than twice the
+/// context.
+fn group_hunks(script: Vec<HunkLine>, context: usize) -> Vec<Hunk> {
+    let changes: Vec<usize> = (0..script.len())
+        .filter(|i| !matches!(script[*i], HunkLine::Context(_)))
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for change in changes {
+        let start = change.saturating_sub(context);
+        let end = (change + context + 1).min(script.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    // Line numbers where each script entry starts, 1-based
+    let mut positions = Vec::with_capacity(script.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in &script {
+        positions.push((old_line, new_line));
+        match line {
+            HunkLine::Context(_) => (old_line, new_line) = (old_line + 1, new_line + 1),
+            HunkLine::Remove(_) => old_line += 1,
+            HunkLine::Add(_) => new_line += 1,
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let lines = script[start..end].to_vec();
+            let old_len = lines
+                .iter()
+                .filter(|l| !matches!(l, HunkLine::Add(_)))
+                .count();
+            let new_len = lines
+                .iter()
+                .filter(|l| !matches!(l, HunkLine::Remove(_)))
+                .count();
+            // Empty side points at the line before, as in `diff -u`
+            let (old_start, new_start) = positions[start];
+            let old_start = old_start - usize::from(old_len == 0);
+            let new_start = new_start - usize::from(new_len == 0);
+            Hunk {
+                header: format!(
+                    "@@ -{},{} +{},{} @@",
+                    old_start, old_len, new_start, new_len
+                ),
+                old_start,
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Colors unified diff for terminal: removed lines red, added green.
+pub fn colorize(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let color = match line {
+                _ if line.starts_with("---") || line.starts_with("+++") => "1",
+                _ if line.starts_with("@@") => "36",
+                _ if line.starts_with('-') => "31",
+                _ if line.starts_with('+') => "32",
+                _ => return format!("{}\n", line),
+            };
+            format!("\x1b[{}m{}\x1b[0m\n", color, line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str =
+        "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+
+    #[test]
+    fn parses_hunks() -> io::Result<()> {
+        let hunks =
+            parse("--- a/x\n+++ b/x\n@@ -2,2 +2,2 @@\n let a = 1;\n-let b = 2;\n+let b = 3;\n")?;
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_start, 2);
+        assert_eq!(
+            hunks[0].lines,
+            vec![
+                HunkLine::Context("let a = 1;".into()),
+                HunkLine::Remove("let b = 2;".into()),
+                HunkLine::Add("let b = 3;".into()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn applies_at_shifted_position() -> io::Result<()> {
+        let existing = format!("// header\n// more\n{}", ORIGINAL);
+        let hunks = parse(
+            "@@ -2,3 +2,3 @@\n     let a = 1;\n-    let b = 2;\n+    let b = 40;\n     println!(\"{}\", a + b);\n",
+        )?;
+        let result = apply(&existing, &hunks, 2);
+        assert!(result.rejected.is_empty());
+        assert_eq!(result.content, existing.replace("b = 2", "b = 40"));
+        Ok(())
+    }
+
+    #[test]
+    fn fuzzy_context() -> io::Result<()> {
+        // First context line differs, whitespace differs in the last one
+        let hunks = parse(
+            "@@ -1,4 +1,4 @@\n fn start() {\n     let a = 1;\n-    let b = 2;\n+    let b = 3;\n   println!(\"{}\",  a + b);\n",
+        )?;
+        let result = apply(ORIGINAL, &hunks, 2);
+        assert!(result.rejected.is_empty());
+        assert_eq!(result.content, ORIGINAL.replace("b = 2", "b = 3"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unmatched_hunk() -> io::Result<()> {
+        let hunks = parse(
+            "@@ -1,2 +1,2 @@\n-let x = 1;\n+let x = 2;\n@@ -3,1 +3,1 @@\n-    let b = 2;\n+    let b = 5;\n",
+        )?;
+        let result = apply(ORIGINAL, &hunks, 2);
+        assert_eq!(result.applied, 1);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.content, ORIGINAL.replace("b = 2", "b = 5"));
+        assert!(format_rejects("main.rs", &result.rejected).contains("-let x = 1;\n+let x = 2;\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn skips_applied_hunks() -> io::Result<()> {
+        let hunks = parse("@@ -1,3 +1,4 @@\n a\n+b\n c\n d\n@@ -4,1 +5,1 @@\n-x\n+y\n")?;
+        let result = apply("a\nb\nc\nd\nx\n", &hunks, 2);
+        assert_eq!(result.already_applied, 1);
+        assert_eq!(result.applied, 1);
+        assert!(result.rejected.is_empty());
+        assert_eq!(result.content, "a\nb\nc\nd\ny\n");
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_crlf() -> io::Result<()> {
+        let hunks = parse("@@ -1,1 +1,2 @@\n a\n+b\n")?;
+        let result = apply("a\r\nc\r\n", &hunks, 0);
+        assert_eq!(result.content, "a\r\nb\r\nc\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn diffs_lines() {
+        let new = ORIGINAL.replace("let b = 2;", "let b = 3;");
+        let hunks = diff(ORIGINAL, &new, 1);
+        assert_eq!(
+            format_diff("a", "b", &hunks),
+            "--- a\n+++ b\n@@ -2,3 +2,3 @@\n     let a = 1;\n-    let b = 2;\n+    let b = 3;\n     println!(\"{}\", a + b);\n"
+        );
+        assert_eq!(diff("", "a\nb\n", 3)[0].header, "@@ -0,0 +1,2 @@");
+        assert!(diff(ORIGINAL, ORIGINAL, 3).is_empty());
+    }
+
+    #[test]
+    fn finds_longest_common_lines() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let pairs = common_lines(&old, &new);
+        assert_eq!(pairs.len(), 4);
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        assert!(pairs.iter().all(|&(i, j)| old[i] == new[j]));
+    }
+}
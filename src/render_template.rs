@@ -1,86 +1,209 @@
 use std::{
     collections::HashMap,
+    env::current_dir,
     fmt::Write,
     fs::{self},
-    path::PathBuf,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
 };
 
 use crate::{
     EchoArgs, MakeArgs,
-    error_handling::UnwrapQuit,
+    conflict::{self, Resolution},
+    edit::{self, Anchor, CommentSyntax},
+    error_handling::{UnwrapQuit, UnwrapReport, quit_with_error},
+    header::{Attributes, Eol},
+    hooks,
+    manifest::{self, Manifest, Record},
+    merge::{self, Format},
+    patch, require,
     template::{
-        Node, get_template_string_from_path, read_template,
-        validate_path_string,
+        BOM, Delimiters, Node, get_template_string_from_path, is_in_library, read_template,
+        resolve_template_path, validate_path_string,
     },
+    three_way,
+    transaction::{Kind, Transaction},
 };
 
-pub fn render(template: &str, ctx: &HashMap<String, String>) -> String {
+pub fn render_with(template: &str, ctx: &HashMap<String, String>, delims: &Delimiters) -> String {
     let mut output = String::with_capacity(template.len());
-    let mut chars = template.chars().peekable();
+    let mut rest = template;
 
-    while let Some(c) = chars.next() {
-        if c == '{' && chars.peek() == Some(&'{') {
-            chars.next();
+    while let Some(start) = rest.find(&delims.open) {
+        output.push_str(&rest[..start]);
+        let after_open = &rest[start + delims.open.len()..];
+        let Some(end) = after_open.find(&delims.close) else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        let inner = &after_open[..end];
+        rest = &after_open[end + delims.close.len()..];
 
-            let mut inner = String::new();
-            while let Some(ic) = chars.next() {
-                if ic == '}' && chars.peek() == Some(&'}') {
-                    chars.next();
-                    break;
-                }
-                inner.push(ic);
-            }
-
-            let parts: Vec<&str> = inner.split('|').map(|s| s.trim()).collect();
+        let parts: Vec<&str> = inner.split('|').map(|s| s.trim()).collect();
 
-            let key = parts[0];
+        let key = parts[0];
 
-            if let Some(val) = ctx.get(key) {
-                let mut res = val.clone();
-                for filter in &parts[1..] {
-                    match *filter {
-                        "upper" => res = res.to_uppercase(),
-                        _ => eprintln!("Unknown filter: {}", filter),
-                    }
+        if let Some(val) = ctx.get(key) {
+            let mut res = val.clone();
+            for filter in &parts[1..] {
+                match *filter {
+                    "upper" => res = res.to_uppercase(),
+                    _ => eprintln!("Unknown filter: {}", filter),
                 }
-                output.push_str(&res);
-            } else {
-                output.push_str("{{");
-                output.push_str(&inner);
-                output.push_str("}}");
             }
-            continue;
+            output.push_str(&res);
+        } else {
+            output.push_str(&delims.open);
+            output.push_str(inner);
+            output.push_str(&delims.close);
         }
-        output.push(c);
     }
+    output.push_str(rest);
     output
 }
 
-fn render_to_file(path_str: &str, content: &str, context: &HashMap<String, String>) {
+fn render_to_file(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    on_conflict: &mut conflict::Policy,
+    transaction: &mut Transaction,
+) {
     let mut context = context.clone();
     update_context_with_magic_vars(&mut context, path_str);
-    let content = render(content, &context);
-    let path_str = render(path_str, &context);
-    let pathbuf =
-        validate_path_string(path_str.as_str()).unwrap_or_quit(1, "Invalid template definition");
-    if let Some(parent_dir) = pathbuf.parent() {
-        _ = fs::create_dir_all(parent_dir);
-    }
-    let content = content.trim();
-    println!("Writing: {}", path_str);
-    assert!(fs::write(pathbuf.as_path(), content).is_ok());
-}
-fn render_or_extend(path_str: &str, content: &str, context: &HashMap<String, String>) {
-    let content = render(content, context);
-    let path_str = render(path_str, context);
-    let pathbuf =
-        validate_path_string(path_str.as_str()).unwrap_or_quit(1, "Invalid template definition");
-    let content = content.trim();
-
-    if pathbuf.exists() {
-        let existing_content =
-            std::fs::read_to_string(&pathbuf).unwrap_or_quit(2, "Can't read file for extension");
-        if existing_content.contains(content) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(&context);
+    let content = finalize_content(render_with(content, &context, &delims), &attrs);
+    let path_str = render_with(path_str, &context, &delims);
+    let mut pathbuf =
+        validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    let existing_content = transaction.read_to_string(&pathbuf).ok();
+    if existing_content.is_some() && attrs.once().unwrap_or_report(1) {
+        transaction.note(&format!("Skipping: {} (already exists)", path_str));
+        return;
+    }
+    let base = recorded_base(&pathbuf, existing_content.as_deref(), transaction);
+    if existing_content.is_none() && base.is_some() && *on_conflict == conflict::Policy::Merge {
+        transaction.note(&format!("Skipping: {} (removed locally)", path_str));
+        transaction.set_generated(&pathbuf, content);
+        return;
+    }
+    let content = match &existing_content {
+        Some(old_content) => {
+            let kept = edit::keep_regions(old_content, &content)
+                .map_err(|err| format!("{} in {}", err, path_str))
+                .unwrap_or_report(1);
+            for id in kept.dropped {
+                eprintln!(
+                    "WARN: {}: kept region `{}` isn't in the template anymore, dropping it",
+                    path_str, id
+                );
+            }
+            kept.content
+        }
+        None => content,
+    };
+    let content = match attrs.bom().unwrap_or_report(1) {
+        true => format!("{}{}", BOM, content),
+        false => content,
+    };
+    transaction.set_generated(&pathbuf, content.clone());
+    let mut path_str = path_str;
+    let mut content = content;
+    if let Some(existing_content) = existing_content.filter(|existing| *existing != content) {
+        match conflict::resolve(
+            &path_str,
+            &existing_content,
+            &content,
+            base.as_deref(),
+            on_conflict,
+        )
+        .unwrap_or_report(1)
+        {
+            Resolution::Overwrite => (),
+            Resolution::Merged(merged) => {
+                if merged.conflicts > 0 {
+                    eprintln!(
+                        "WARN: {}: {} conflicting changes, look for `{}` markers",
+                        path_str,
+                        merged.conflicts,
+                        three_way::LOCAL_MARKER
+                    );
+                }
+                content = merged.content;
+            }
+            Resolution::Skip => {
+                transaction.note(&format!("Skipping: {} (keeping local changes)", path_str));
+                return;
+            }
+            Resolution::Rename(renamed) => {
+                (path_str, pathbuf) = free_rename(&renamed, &content, transaction);
+            }
+        }
+    }
+    transaction.note(&format!("Writing: {}", path_str));
+    transaction
+        .write(&pathbuf, content, file_mode(&pathbuf, &attrs))
+        .unwrap_or_report(1);
+}
+/// `renamed`, or `renamed.1`, `renamed.2`... when it's taken: first path
+/// which doesn't exist or already holds `content`, so that renaming never
+/// overwrites a file.
+fn free_rename(renamed: &str, content: &str, transaction: &Transaction) -> (String, PathBuf) {
+    let mut candidate = renamed.to_string();
+    let mut n = 0;
+    loop {
+        let path = validate_path_string(transaction.root(), &candidate).unwrap_or_report(1);
+        let free = !transaction.exists(&path)
+            || transaction
+                .read_to_string(&path)
+                .is_ok_and(|existing| existing == content);
+        if free {
+            return (candidate, path);
+        }
+        n += 1;
+        candidate = format!("{}.{}", renamed, n);
+    }
+}
+
+/// What the template generated at `path` last time: its base copy, or the
+/// file itself while it still has the recorded hash. Files holding secrets
+/// have no base copy.
+fn recorded_base(path: &Path, existing: Option<&str>, transaction: &Transaction) -> Option<String> {
+    if let Ok(base) = transaction.read_to_string(&manifest::base_path(path)) {
+        return Some(base);
+    }
+    let existing = existing?;
+    let text = transaction.read_to_string(Path::new(manifest::PATH)).ok()?;
+    let manifest = Manifest::parse(&text).ok()?;
+    let hash = manifest::hash(existing.as_bytes());
+    manifest
+        .templates
+        .iter()
+        .flat_map(|record| &record.files)
+        .any(|(file, recorded)| Path::new(file) == path && *recorded == hash)
+        .then(|| existing.to_string())
+}
+
+fn render_or_extend(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = finalize_content(render_with(content, context, &delims), &attrs);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+
+    if transaction.exists(&pathbuf) {
+        let existing_content = transaction
+            .read_to_string(&pathbuf)
+            .unwrap_or_quit(2, "Can't read file for extension");
+        if edit::already_contains(&existing_content, &content) {
             eprintln!(
                 "WARN: {} already contains identical content, not extending!",
                 pathbuf.to_string_lossy()
@@ -89,60 +212,905 @@ fn render_or_extend(path_str: &str, content: &str, context: &HashMap<String, Str
         }
         let mut new_content = String::new();
         let _ = new_content.write_str(&existing_content);
-        write!(new_content, "\n{}", content).unwrap_or_quit(1, "Can't extend content");
-        println!("Extending: {}", path_str);
-        assert!(fs::write(pathbuf.as_path(), new_content).is_ok());
+        if !existing_content.is_empty() && !existing_content.ends_with('\n') {
+            let eol = Eol::detect(&existing_content).unwrap_or(Eol::Lf);
+            new_content.push_str(eol.as_str());
+        }
+        new_content
+            .write_str(&content)
+            .unwrap_or_quit(1, "Can't extend content");
+        transaction.note(&format!("Extending: {}", path_str));
+        transaction
+            .write(&pathbuf, new_content, None)
+            .unwrap_or_report(1);
     } else {
-        if let Some(parent_dir) = pathbuf.parent() {
-            _ = fs::create_dir_all(parent_dir);
+        transaction.note(&format!("Writing: {}", path_str));
+        transaction
+            .write(&pathbuf, content, None)
+            .unwrap_or_report(1);
+    }
+}
+
+fn render_or_prepend(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = finalize_content(render_with(content, context, &delims), &attrs);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+
+    if transaction.exists(&pathbuf) {
+        let existing_content = transaction
+            .read_to_string(&pathbuf)
+            .unwrap_or_quit(2, "Can't read file for prepending");
+        if edit::already_contains(&existing_content, &content) {
+            eprintln!(
+                "WARN: {} already contains identical content, not prepending!",
+                pathbuf.to_string_lossy()
+            );
+            return;
         }
-        println!("Writing: {}", path_str);
-        assert!(fs::write(pathbuf.as_path(), content).is_ok());
+        transaction.note(&format!("Prepending: {}", path_str));
+        let new_content = edit::prepend(&existing_content, &content);
+        transaction
+            .write(&pathbuf, new_content, None)
+            .unwrap_or_report(1);
+    } else {
+        transaction.note(&format!("Writing: {}", path_str));
+        transaction
+            .write(&pathbuf, content, None)
+            .unwrap_or_report(1);
     }
 }
 
-pub(crate) fn make(args: &MakeArgs) {
-    let template_result = read_template(&args.template_path);
-    let Ok(template_entities) = template_result else {
-        eprintln!("Error: {}", template_result.unwrap_err());
+fn render_block(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = finalize_content(render_with(content, context, &delims), &attrs);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    let id = attrs.get("id").unwrap_or_default();
+    let syntax = match attrs.get("comment") {
+        Some(comment) => CommentSyntax::parse(comment),
+        None => CommentSyntax::for_path(&pathbuf),
+    };
+
+    let existing_content = match transaction.exists(&pathbuf) {
+        true => transaction.read_to_string(&pathbuf).unwrap_or_quit(
+            2,
+            &format!("Can't read file for block update: {}", path_str),
+        ),
+        false => String::new(),
+    };
+    let new_content = edit::replace_block(&existing_content, &content, id, &syntax)
+        .map_err(|err| format!("{} in {}", err, path_str))
+        .unwrap_or_report(1);
+    if new_content == existing_content {
+        eprintln!(
+            "WARN: {} already contains identical `{}` block, not updating!",
+            pathbuf.to_string_lossy(),
+            id
+        );
+        return;
+    }
+    match existing_content.is_empty() {
+        true => transaction.note(&format!("Writing: {}", path_str)),
+        false => transaction.note(&format!("Updating block: {} ({})", path_str, id)),
+    }
+    transaction
+        .write(&pathbuf, new_content, None)
+        .unwrap_or_report(1);
+}
+
+fn render_insert(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = finalize_content(render_with(content, context, &delims), &attrs);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    let anchor = Anchor::from_attrs(&attrs).unwrap_or_report(1);
+
+    let existing_content = transaction
+        .read_to_string(&pathbuf)
+        .unwrap_or_quit(2, &format!("Can't read file for insertion: {}", path_str));
+    let new_content = edit::insert(&existing_content, &content, &anchor)
+        .map_err(|err| format!("{} in {}", err, path_str))
+        .unwrap_or_report(1);
+    match new_content {
+        None => eprintln!(
+            "WARN: {} already contains identical content, not inserting!",
+            pathbuf.to_string_lossy()
+        ),
+        Some(new_content) => {
+            transaction.note(&format!("Inserting: {}", path_str));
+            transaction
+                .write(&pathbuf, new_content, None)
+                .unwrap_or_report(1);
+        }
+    }
+}
+
+fn render_merge(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = render_with(content, context, &delims);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    let format = match attrs.get("format") {
+        Some(format) => format.parse().map_err(io::Error::other),
+        None => Format::from_path(&pathbuf).ok_or_else(|| {
+            io::Error::other(format!(
+                "Can't tell format of {}, set format=json|toml|yaml",
+                path_str
+            ))
+        }),
+    }
+    .unwrap_or_report(1);
+
+    let Ok(existing_content) = transaction.read_to_string(&pathbuf) else {
+        transaction.note(&format!("Writing: {}", path_str));
+        transaction
+            .write(&pathbuf, content, None)
+            .unwrap_or_report(1);
         return;
     };
+    let merged = merge::merge(
+        &existing_content,
+        &content,
+        format,
+        attrs.on_conflict().unwrap_or_report(1),
+    )
+    .map_err(|err| format!("{} in {}", err, path_str))
+    .unwrap_or_report(1);
+    for key in &merged.kept {
+        eprintln!("WARN: {}: keeping existing value of `{}`", path_str, key);
+    }
+    for key in &merged.overridden {
+        eprintln!("WARN: {}: overriding value of `{}`", path_str, key);
+    }
+    if merged.content == existing_content {
+        eprintln!(
+            "WARN: {} already contains merged content, not merging!",
+            path_str
+        );
+        return;
+    }
+    transaction.note(&format!("Merging: {}", path_str));
+    transaction
+        .write(&pathbuf, merged.content, None)
+        .unwrap_or_report(1);
+}
+
+fn render_patch(
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let attrs = attrs.render(context);
+    let content = render_with(content, context, &delims);
+    let path_str = render_with(path_str, context, &delims);
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    let hunks = patch::parse(&content)
+        .map_err(|err| format!("{} (PATCH {})", err, path_str))
+        .unwrap_or_report(1);
+
+    let existing_content = transaction
+        .read_to_string(&pathbuf)
+        .unwrap_or_quit(2, &format!("Can't read file for patching: {}", path_str));
+    let result = patch::apply(&existing_content, &hunks, attrs.fuzz().unwrap_or_report(1));
+    if result.already_applied > 0 {
+        transaction.note(&format!(
+            "Skipping: {} ({} out of {} hunks already applied)",
+            path_str,
+            result.already_applied,
+            hunks.len()
+        ));
+    }
+    if result.applied > 0 {
+        transaction.note(&format!("Patching: {}", path_str));
+        transaction
+            .write(&pathbuf, result.content, None)
+            .unwrap_or_report(1);
+    }
+    if !result.rejected.is_empty() && transaction.is_preview() {
+        eprintln!(
+            "WARN: {} out of {} hunks would FAIL for {}",
+            result.rejected.len(),
+            hunks.len(),
+            path_str
+        );
+    } else if !result.rejected.is_empty() {
+        let rej_str = format!("{}.rej", path_str);
+        let rej_path = validate_path_string(transaction.root(), &rej_str).unwrap_or_report(1);
+        // Hunks that did apply are rolled back too, so all of them are saved
+        let rejects = patch::format_rejects(&path_str, &hunks);
+        // Everything else is rolled back, only rejects are kept
+        let mut rejects_transaction = Transaction::new(transaction.root());
+        rejects_transaction
+            .write(&rej_path, rejects, None)
+            .unwrap_or_report(1);
+        rejects_transaction
+            .commit()
+            .unwrap_or_quit(2, &format!("Can't write {}", rej_str));
+        quit_with_error(
+            1,
+            &format!(
+                "{} out of {} hunks FAILED for {} -- saving all hunks to {}",
+                result.rejected.len(),
+                hunks.len(),
+                path_str,
+                rej_str
+            ),
+        );
+    }
+}
+
+fn render_remove(
+    path_str: &str,
+    attrs: &Attributes,
+    args: &MakeArgs,
+    transaction: &mut Transaction,
+) {
+    let path_str = render_with(path_str, &args.variables, &attrs.delimiters());
+    let pathbuf = validate_path_string(transaction.root(), path_str.as_str()).unwrap_or_report(1);
+    if !transaction.exists(&pathbuf) {
+        eprintln!("WARN: {} doesn't exist, nothing to remove", path_str);
+        return;
+    }
+    if !confirm(&format!("Remove {}?", path_str), args.yes) {
+        transaction.note(&format!("Skipping: {}", path_str));
+        return;
+    }
+    transaction.note(&format!("Removing: {}", path_str));
+    transaction.remove(&pathbuf);
+}
+
+fn render_move(
+    from_str: &str,
+    to_str: &str,
+    attrs: &Attributes,
+    args: &MakeArgs,
+    transaction: &mut Transaction,
+) {
+    let delims = attrs.delimiters();
+    let from_str = render_with(from_str, &args.variables, &delims);
+    let to_str = render_with(to_str, &args.variables, &delims);
+    let from = validate_path_string(transaction.root(), from_str.as_str()).unwrap_or_report(1);
+    let to = validate_path_string(transaction.root(), to_str.as_str()).unwrap_or_report(1);
+
+    match (transaction.exists(&from), transaction.exists(&to)) {
+        (false, true) => {
+            eprintln!("WARN: {} already moved to {}, skipping", from_str, to_str);
+            return;
+        }
+        (false, false) => quit_with_error(2, &format!("Can't move {}: not found", from_str)),
+        (true, true) => quit_with_error(
+            2,
+            &format!("Can't move {}: {} already exists", from_str, to_str),
+        ),
+        (true, false) => (),
+    }
+    if !confirm(&format!("Move {} -> {}?", from_str, to_str), args.yes) {
+        transaction.note(&format!("Skipping: {}", from_str));
+        return;
+    }
+    transaction.note(&format!("Moving: {} -> {}", from_str, to_str));
+    transaction.rename(&from, &to).unwrap_or_report(2);
+}
+
+/// Fills variables declared with `VAR` sections which weren't given on the
+/// command line: asks for them on a terminal, otherwise takes the default.
+/// Defaults can use variables declared before.
+fn declare_variables(nodes: &[Node], args: &MakeArgs) -> HashMap<String, String> {
+    let mut variables = args.variables.clone();
+    let interactive = !args.yes && io::stdin().is_terminal();
+    for node in nodes {
+        let Node::Var { name, attrs } = node else {
+            continue;
+        };
+        let attrs = attrs.render(&variables);
+        let choices: Vec<&str> = attrs
+            .get("choices")
+            .map(|c| c.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        let default = attrs
+            .get("default")
+            .or(choices.first().copied())
+            .map(String::from);
+        let value = match variables.get(name) {
+            Some(value) => value.clone(),
+            None if interactive => {
+                let prompt = attrs.get("prompt").unwrap_or(name);
+                ask_variable(prompt, &choices, default)
+            }
+            None => default.unwrap_or_else(|| {
+                quit_with_error(
+                    1,
+                    &format!("Variable `{}` is not set, pass {}=VALUE", name, name),
+                );
+                unreachable!();
+            }),
+        };
+        if !choices.is_empty() && !choices.contains(&value.as_str()) {
+            quit_with_error(
+                1,
+                &format!(
+                    "Variable `{}` is `{}`, expected one of: {}",
+                    name,
+                    value,
+                    choices.join(", ")
+                ),
+            );
+        }
+        variables.insert(name.clone(), value);
+    }
+    variables
+}
+
+fn ask_variable(prompt: &str, choices: &[&str], default: Option<String>) -> String {
+    if !choices.is_empty() {
+        let selected = choices
+            .iter()
+            .position(|c| Some(*c) == default.as_deref())
+            .unwrap_or(0);
+        let index = dialoguer::Select::new()
+            .with_prompt(prompt)
+            .items(choices)
+            .default(selected)
+            .interact()
+            .unwrap_or_quit(1, "Can't ask for variable value");
+        return choices[index].into();
+    }
+    let mut input = dialoguer::Input::<String>::new().with_prompt(prompt);
+    if let Some(default) = default {
+        input = input.default(default);
+    }
+    input
+        .interact_text()
+        .unwrap_or_quit(1, "Can't ask for variable value")
+}
+
+fn render_dir_path(
+    path: &Path,
+    attrs: &Attributes,
+    variables: &HashMap<String, String>,
+    root: &Path,
+) -> PathBuf {
+    let path_str = render_with(&path.to_string_lossy(), variables, &attrs.delimiters());
+    validate_path_string(root, path_str.as_str()).unwrap_or_report(1)
+}
+
+/// Directory the template is rendered into: `--output` or current
+/// directory, inside `{{ name }}/` with `--into-name`.
+fn output_root(args: &MakeArgs) -> PathBuf {
+    let base = match &args.output {
+        Some(output) => output.clone(),
+        None => current_dir().unwrap_or_quit(2, "Can't get current dir"),
+    };
+    if !args.into_name {
+        return base;
+    }
+    let name = args.variables.get("name").map(String::as_str);
+    base.join(validate_path_string(&base, name.unwrap_or_default()).unwrap_or_report(1))
+}
+
+/// Checks all `REQUIRE` sections up front, so nothing is written when the
+/// template can't be applied. Paths are checked inside output `root`.
+fn check_requirements(nodes: &[Node], variables: &HashMap<String, String>, root: &Path) {
+    let mut reasons: Vec<String> = Vec::new();
+    for node in nodes {
+        let Node::Require { args, attrs } = node else {
+            continue;
+        };
+        let delims = attrs.delimiters();
+        let attrs = attrs.render(variables);
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| render_with(arg, variables, &delims))
+            .collect();
+        let failed: Vec<String> = require::parse(&args)
+            .unwrap_or_report(1)
+            .iter()
+            .filter_map(|requirement| require::check(requirement, variables, root))
+            .collect();
+        match attrs.get("message") {
+            Some(message) if !failed.is_empty() => reasons.push(message.into()),
+            _ => reasons.extend(failed),
+        }
+    }
+    if !reasons.is_empty() {
+        quit_with_error(
+            1,
+            &format!("Template requirements not met:\n- {}", reasons.join("\n- ")),
+        );
+    }
+}
+
+/// Runs `RUN` sections once everything else is in place. Hooks of templates
+/// from outside of user's templates directory need to be confirmed.
+/// Renders `RUN` body with shell-quoted variable values, so they can't
+/// inject commands.
+fn render_hook(content: &str, attrs: &Attributes, variables: &HashMap<String, String>) -> String {
+    let quoted: HashMap<String, String> = variables
+        .iter()
+        .map(|(name, value)| (name.clone(), hooks::quote(value)))
+        .collect();
+    render_with(content, &quoted, &attrs.delimiters())
+}
+
+/// Whether hooks should run; asks about templates from outside the library.
+/// Called before anything is written, so declining leaves no files behind.
+fn confirm_hooks(hooks: &[String], args: &MakeArgs) -> bool {
+    if hooks.is_empty() {
+        return false;
+    }
+    // `update` passes --no-hooks itself
+    if args.no_hooks && args.on_conflict == conflict::Policy::Merge {
+        println!("Skipping hooks: run by make already");
+        return false;
+    }
+    if args.no_hooks {
+        println!("Skipping hooks: --no-hooks given");
+        return false;
+    }
+    if !is_in_library(&args.template_path) {
+        let prompt = format!(
+            "{} is not in your templates directory and wants to run:\n{}\nRun these commands?",
+            args.template_path.to_string_lossy(),
+            hooks.join("\n").trim_end()
+        );
+        if !confirm(&prompt, args.yes) {
+            println!("Skipping hooks");
+            return false;
+        }
+    }
+    true
+}
+
+fn run_hooks(hooks: &[String], args: &MakeArgs, root: &Path) {
+    for script in hooks {
+        println!("Running: {}", script.trim());
+        hooks::run(script, &args.variables, root).unwrap_or_report(1);
+    }
+}
+
+/// Asks before destructive operations, unless `--yes` was given.
+fn confirm(prompt: &str, yes: bool) -> bool {
+    if yes {
+        return true;
+    }
+    dialoguer::Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap_or_quit(1, "Can't ask for confirmation, use --yes to proceed")
+}
+
+/// Applies `trim`, `final_newline` and `eol` attributes. Without them content
+/// is written exactly as in the template.
+fn finalize_content(content: String, attrs: &Attributes) -> String {
+    let mut content = match attrs.trim().unwrap_or_report(1) {
+        true => content.trim().to_string(),
+        false => content,
+    };
+    match attrs.final_newline().unwrap_or_report(1) {
+        Some(true) if !content.ends_with('\n') => content.push('\n'),
+        Some(false) => content.truncate(content.trim_end_matches(['\r', '\n']).len()),
+        _ => (),
+    }
+    match attrs.eol().unwrap_or_report(1) {
+        Some(eol) => eol.normalize(&content),
+        None => content,
+    }
+}
+
+pub(crate) fn make(args: &MakeArgs) {
+    // Resolved once, so the manifest records where the template really is
+    let template_path = resolve_template_path(&args.template_path).unwrap_or_report(1);
+    let mut template_entities = read_template(&template_path).unwrap_or_report(1);
+    // Nothing is changed with --diff, so there's nothing to confirm
+    let args = &MakeArgs {
+        template_path,
+        variables: declare_variables(&template_entities, args),
+        yes: args.yes || args.diff,
+        ..args.clone()
+    };
+
+    if let Some(eol) = args.eol {
+        for node in template_entities.iter_mut() {
+            if node.attrs().get("eol").is_none()
+                && node.content_mut().is_some()
+                && !matches!(node, Node::Run { .. })
+            {
+                node.attrs_mut().insert("eol", &eol.to_string());
+            }
+        }
+    }
+
+    let template_entities: Vec<Node> = template_entities
+        .into_iter()
+        .filter(|node| {
+            node.attrs()
+                .render(&args.variables)
+                .condition(&args.variables)
+        })
+        .collect();
+    let root = output_root(args);
+    check_requirements(&template_entities, &args.variables, &root);
 
     if args.dry_run {
         // Dry Run
         for entity in template_entities {
             match entity {
-                Node::File { path, content } | Node::Ext { path, content } => {
-                    preview_file(&path, &content, &args.variables)
+                Node::File {
+                    path,
+                    content,
+                    attrs,
+                }
+                | Node::Ext {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("FILE", &path, &content, &attrs, &args.variables),
+                Node::Prepend {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("PREPEND", &path, &content, &attrs, &args.variables),
+                Node::Insert {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("INSERT", &path, &content, &attrs, &args.variables),
+                Node::Patch {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("PATCH", &path, &content, &attrs, &args.variables),
+                Node::Merge {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("MERGE", &path, &content, &attrs, &args.variables),
+                Node::Block {
+                    path,
+                    content,
+                    attrs,
+                } => preview_file("BLOCK", &path, &content, &attrs, &args.variables),
+                Node::Dir { path, attrs } => {
+                    let path = render_dir_path(&path, &attrs, &args.variables, &root);
+                    println!("\n{{### DIR {} ###}}", path.to_str().unwrap())
+                }
+                Node::Remove { path, attrs } => {
+                    let path = render_with(&path, &args.variables, &attrs.delimiters());
+                    println!("\n{{### REMOVE {} ###}}", path)
+                }
+                Node::Move { from, to, attrs } => {
+                    let delims = attrs.delimiters();
+                    let from = render_with(&from, &args.variables, &delims);
+                    let to = render_with(&to, &args.variables, &delims);
+                    println!("\n{{### MOVE {} -> {} ###}}", from, to)
+                }
+                Node::Require { .. } | Node::Var { .. } => (),
+                Node::Run { content, attrs } => {
+                    let content = render_hook(&content, &attrs, &args.variables);
+                    println!("\n{{### RUN ###}}");
+                    print!("{}", content);
+                    if !content.ends_with('\n') {
+                        println!();
+                    }
                 }
-                Node::Dir(path) => println!("\n{{### DIR {} ###}}", path.to_str().unwrap()),
             }
         }
     } else {
-        // Materialize
+        // Materialize: everything is staged first, so an error on the way
+        // leaves the target untouched
+        let mut transaction = match args.diff {
+            true => Transaction::preview(&root),
+            false => Transaction::new(&root),
+        };
+        let mut on_conflict = match (args.diff, args.on_conflict) {
+            (true, conflict::Policy::Prompt) => conflict::Policy::Overwrite,
+            (_, policy) => policy,
+        };
+        let mut hooks = Vec::new();
+        let secrets = manifest::secrets(&template_entities);
+        // `update` renders the template again, edits would be applied twice
+        let update = args.on_conflict == conflict::Policy::Merge;
+        let mut skipped = Vec::new();
         for entity in template_entities {
             match entity {
-                Node::File { path, content } => render_to_file(&path, &content, &args.variables),
-                Node::Dir(path) => {
-                    println!("Creating dir: {}", path.to_str().expect("Can't create dir"));
-                    _ = fs::create_dir_all(path);
+                Node::Ext { path, attrs, .. }
+                | Node::Prepend { path, attrs, .. }
+                | Node::Insert { path, attrs, .. }
+                | Node::Patch { path, attrs, .. }
+                    if update =>
+                {
+                    let path = render_with(&path, &args.variables, &attrs.delimiters());
+                    transaction.note(&format!("Skipping: {} (edited by make already)", path));
+                    skipped.push(PathBuf::from(path));
+                }
+                Node::File {
+                    path,
+                    content,
+                    attrs,
+                } => render_to_file(
+                    &path,
+                    &content,
+                    &attrs,
+                    &args.variables,
+                    &mut on_conflict,
+                    &mut transaction,
+                ),
+                Node::Dir { path, attrs } => {
+                    let path = render_dir_path(&path, &attrs, &args.variables, &root);
+                    transaction.note(&format!(
+                        "Creating dir: {}",
+                        path.to_str().expect("Can't create dir")
+                    ));
+                    let mode = file_mode(&path, &attrs.render(&args.variables));
+                    transaction.create_dir(&path, mode).unwrap_or_report(1);
+                }
+                Node::Ext {
+                    path,
+                    content,
+                    attrs,
+                } => render_or_extend(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Prepend {
+                    path,
+                    content,
+                    attrs,
+                } => render_or_prepend(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Insert {
+                    path,
+                    content,
+                    attrs,
+                } => render_insert(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Patch {
+                    path,
+                    content,
+                    attrs,
+                } => render_patch(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Merge {
+                    path,
+                    content,
+                    attrs,
+                } => render_merge(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Block {
+                    path,
+                    content,
+                    attrs,
+                } => render_block(&path, &content, &attrs, &args.variables, &mut transaction),
+                Node::Remove { path, attrs } => {
+                    render_remove(&path, &attrs, args, &mut transaction)
+                }
+                Node::Move { from, to, attrs } => {
+                    render_move(&from, &to, &attrs, args, &mut transaction)
+                }
+                Node::Require { .. } | Node::Var { .. } => (),
+                Node::Run { content, attrs } => {
+                    hooks.push(render_hook(&content, &attrs, &args.variables))
                 }
-                Node::Ext { path, content } => render_or_extend(&path, &content, &args.variables),
             }
         }
+        if args.diff {
+            print_diff(&transaction, &hooks);
+            return;
+        }
+        let run = confirm_hooks(&hooks, args);
+        record_manifest(args, &secrets, &skipped, &mut transaction);
+        transaction
+            .commit()
+            .map_err(|err| format!("{}, no changes were made", err))
+            .unwrap_or_report(2);
+        if run {
+            run_hooks(&hooks, args, &root);
+        }
     }
 }
 
-fn preview_file(path_str: &str, content: &str, context: &HashMap<String, String>) {
+/// Stages record of this run in `.tmplr/manifest.toml`, next to records of
+/// other templates made into the directory, and the base copies of
+/// generated files under `.tmplr/base`, so they're committed together with
+/// the files they describe. Files of `skipped` edits keep their entries.
+fn record_manifest(
+    args: &MakeArgs,
+    secrets: &[String],
+    skipped: &[PathBuf],
+    transaction: &mut Transaction,
+) {
+    let template = get_template_string_from_path(&args.template_path).unwrap_or_report(1);
+    let mut record = Record::new(
+        &args.template_path,
+        &template,
+        secrets,
+        &args.variables,
+        transaction,
+    );
+    let path = Path::new(manifest::PATH);
+    let mut manifest = match transaction.read_to_string(path) {
+        Ok(text) => Manifest::parse(&text).unwrap_or_report(1),
+        Err(_) => Manifest::default(),
+    };
+    // Bases of files other templates wrote stay theirs, so updating them
+    // keeps what this one added as a local change
+    let others: Vec<PathBuf> = manifest
+        .templates
+        .iter()
+        .filter(|other| other.name != record.name)
+        .flat_map(|other| other.files.iter().map(|(file, _)| PathBuf::from(file)))
+        .collect();
+    let files: Vec<PathBuf> = record.files.iter().map(|(file, _)| file.into()).collect();
+    if let Some(previous) = manifest.find(&record.name) {
+        for (file, hash) in &previous.files {
+            if skipped.iter().any(|path| Path::new(file) == path)
+                && !files.iter().any(|path| Path::new(file) == path)
+            {
+                record.files.push((file.clone(), hash.clone()));
+            }
+        }
+    }
+    manifest.tmplr_version = crate::VERSION.into();
+    manifest.record(record);
+    transaction
+        .write(path, manifest.format(), None)
+        .unwrap_or_report(1);
+
+    // Secrets stay out of `.tmplr`, `update` compares such files with their
+    // recorded hash instead
+    let secret_values = manifest::secret_values(secrets, &args.variables);
+    let mut bases: Vec<(PathBuf, String)> = transaction
+        .generated()
+        .iter()
+        .map(|(path, content)| (path.clone(), content.clone()))
+        .collect();
+    for path in files {
+        if !transaction.generated().contains_key(&path)
+            && !others.contains(&path)
+            && let Ok(content) = transaction.read_to_string(&path)
+        {
+            bases.push((path, content));
+        }
+    }
+    for (path, content) in bases {
+        let base_path = manifest::base_path(&path);
+        if secret_values.iter().any(|value| content.contains(value)) {
+            if transaction.exists(&base_path) {
+                transaction.remove(&base_path);
+            }
+            continue;
+        }
+        transaction
+            .write(&base_path, content, None)
+            .unwrap_or_report(1);
+    }
+}
+
+/// Prints what `make` would change as unified diff against the files on
+/// disk, marking each path as new, modified, unchanged or removed.
+fn print_diff(transaction: &Transaction, hooks: &[String]) {
+    let color = io::stdout().is_terminal();
+    let paint = |code: &str, text: String| match color {
+        true => format!("\x1b[{}m{}\x1b[0m", code, text),
+        false => text,
+    };
+    for path in transaction.touched() {
+        let disk_path = transaction.root().join(&path);
+        let old = fs::symlink_metadata(&disk_path)
+            .ok()
+            .map(|metadata| match metadata.is_dir() {
+                true => Kind::Dir,
+                false => Kind::File,
+            });
+        let new = transaction.kind(&path);
+        let old_content = match old {
+            Some(Kind::File) => fs::read_to_string(&disk_path).ok(),
+            _ => None,
+        };
+        let new_content = match new {
+            Some(Kind::File) => transaction.read_to_string(&path).ok(),
+            _ => None,
+        };
+        let (status, code) = match (old, new) {
+            (None, None) => continue,
+            (None, Some(_)) => ("new", "32"),
+            (Some(_), None) => ("removed", "31"),
+            _ if old == new && old_content == new_content => ("unchanged", "2"),
+            _ => ("modified", "33"),
+        };
+        let name = path.to_string_lossy();
+        let slash = match old == Some(Kind::Dir) || new == Some(Kind::Dir) {
+            true => "/",
+            false => "",
+        };
+        println!("{}", paint(code, format!("{}: {}{}", status, name, slash)));
+        if status == "unchanged" || (old_content.is_none() && new_content.is_none()) {
+            continue;
+        }
+        let old_name = match old_content {
+            Some(_) => name.to_string(),
+            None => "/dev/null".into(),
+        };
+        let new_name = match new_content {
+            Some(_) => name.to_string(),
+            None => "/dev/null".into(),
+        };
+        let hunks = patch::diff(
+            old_content.as_deref().unwrap_or_default(),
+            new_content.as_deref().unwrap_or_default(),
+            3,
+        );
+        let diff = patch::format_diff(&old_name, &new_name, &hunks);
+        match color {
+            true => print!("{}", patch::colorize(&diff)),
+            false => print!("{}", diff),
+        }
+    }
+    for script in hooks {
+        println!("run: {}", script.trim());
+    }
+}
+
+/// Permissions from `mode` attribute, which only Unix supports.
+fn file_mode(path: &Path, attrs: &Attributes) -> Option<u32> {
+    let mode = attrs.mode().unwrap_or_report(1)?;
+    if cfg!(not(unix)) {
+        eprintln!(
+            "WARN: mode={:o} is not supported on this platform, ignoring for {}",
+            mode,
+            path.to_string_lossy()
+        );
+        return None;
+    }
+    Some(mode)
+}
+
+fn preview_file(
+    cmd: &str,
+    path_str: &str,
+    content: &str,
+    attrs: &Attributes,
+    context: &HashMap<String, String>,
+) {
     let mut context = context.clone();
-    let path_str = render(path_str, &context);
+    let delims = attrs.delimiters();
+    let path_str = render_with(path_str, &context, &delims);
 
     update_context_with_magic_vars(&mut context, &path_str);
 
-    let content = render(content, &context);
-    let content = content.trim();
-    println!("\n{{### FILE {} ###}}", path_str);
-    println!("{}", content);
+    let attrs = attrs.render(&context);
+    let content = finalize_content(render_with(content, &context, &delims), &attrs);
+    println!("\n{{### {} {} ###}}", cmd, path_str);
+    print!("{}", content);
+    if !content.ends_with('\n') {
+        println!();
+    }
 }
 
 pub fn echo(echo_args: &EchoArgs) {
@@ -0,0 +1,208 @@
+use std::{cmp::Ordering, collections::HashMap, env, io, path::Path};
+
+use regex::Regex;
+
+use crate::template::validate_path_string;
+
+/// Precondition declared by `REQUIRE` section, checked before anything is
+/// written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Requirement {
+    Exists(String),
+    Missing(String),
+    Command(String),
+    Var { name: String, value: Option<String> },
+    Version(String),
+}
+
+/// Parses `REQUIRE` arguments: `exists PATH...`, `missing PATH...`,
+/// `command NAME...`, `var NAME [VALUE]` or `tmplr VERSION`.
+pub fn parse(args: &[String]) -> io::Result<Vec<Requirement>> {
+    let Some((kind, rest)) = args.split_first() else {
+        return Err(io::Error::other("Missing requirement in REQUIRE section"));
+    };
+    let missing_args = || io::Error::other(format!("REQUIRE {} expects arguments", kind));
+    let result = match (kind.as_str(), rest) {
+        (_, []) => return Err(missing_args()),
+        ("exists", paths) => paths.iter().cloned().map(Requirement::Exists).collect(),
+        ("missing", paths) => paths.iter().cloned().map(Requirement::Missing).collect(),
+        ("command", names) => names.iter().cloned().map(Requirement::Command).collect(),
+        ("var", [name]) => vec![Requirement::Var {
+            name: name.clone(),
+            value: None,
+        }],
+        ("var", [name, value]) => vec![Requirement::Var {
+            name: name.clone(),
+            value: Some(value.clone()),
+        }],
+        ("tmplr", [version]) => {
+            let version = version.trim_start_matches(">=");
+            parse_version(version)?;
+            vec![Requirement::Version(version.into())]
+        }
+        ("var" | "tmplr", _) => {
+            return Err(io::Error::other(format!(
+                "Unexpected arguments in REQUIRE {}: {}",
+                kind,
+                rest.join(" ")
+            )));
+        }
+        _ => {
+            return Err(io::Error::other(format!(
+                "Unknown requirement: {} (expected exists, missing, command, var or tmplr)",
+                kind
+            )));
+        }
+    };
+    Ok(result)
+}
+
+/// Returns reason why requirement isn't met. Paths are relative to output
+/// directory `root` and have to stay inside it like output paths.
+pub fn check(
+    requirement: &Requirement,
+    variables: &HashMap<String, String>,
+    root: &Path,
+) -> Option<String> {
+    let exists = |path: &str| validate_path_string(root, path).map(|p| root.join(p).exists());
+    match requirement {
+        Requirement::Exists(path) => match exists(path) {
+            Ok(true) => None,
+            Ok(false) => Some(format!("{} doesn't exist", path)),
+            Err(err) => Some(err.to_string()),
+        },
+        Requirement::Missing(path) => match exists(path) {
+            Ok(false) => None,
+            Ok(true) => Some(format!("{} already exists", path)),
+            Err(err) => Some(err.to_string()),
+        },
+        Requirement::Command(name) if !on_path(name) => {
+            Some(format!("command `{}` not found on PATH", name))
+        }
+        Requirement::Var { name, value } => {
+            let actual = variables.get(name).map(String::as_str).unwrap_or("");
+            match value {
+                None if actual.is_empty() => Some(format!("variable `{}` is not set", name)),
+                Some(expected) if !value_matches(actual, expected) => Some(format!(
+                    "variable `{}` is `{}`, expected {}",
+                    name, actual, expected
+                )),
+                _ => None,
+            }
+        }
+        Requirement::Version(version) => {
+            let required = parse_version(version).ok()?;
+            let current = parse_version(crate::VERSION).ok()?;
+            match compare_versions(&current, &required) {
+                Ordering::Less => Some(format!(
+                    "tmplr {} or newer required, this is {}",
+                    version,
+                    crate::VERSION
+                )),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Expected values written as `/.../` are regular expressions which have to
+/// match whole value.
+fn value_matches(actual: &str, expected: &str) -> bool {
+    match expected.strip_prefix('/').and_then(|e| e.strip_suffix('/')) {
+        Some(re) => Regex::new(&format!("^(?:{})$", re)).is_ok_and(|re| re.is_match(actual)),
+        None => actual == expected,
+    }
+}
+
+fn on_path(name: &str) -> bool {
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        return Path::new(name).is_file();
+    }
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+    let extensions: Vec<String> = match cfg!(windows) {
+        true => env::var("PATHEXT")
+            .unwrap_or(".EXE;.BAT;.CMD".into())
+            .split(';')
+            .map(String::from)
+            .chain([String::new()])
+            .collect(),
+        false => vec![String::new()],
+    };
+    env::split_paths(&paths).any(|dir| {
+        extensions
+            .iter()
+            .any(|ext| dir.join(format!("{}{}", name, ext)).is_file())
+    })
+}
+
+fn parse_version(version: &str) -> io::Result<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| io::Error::other(format!("Invalid version: {}", version)))
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    let len = a.len().max(b.len());
+    let part = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| part(a, i).cmp(&part(b, i)))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_requirements() -> io::Result<()> {
+        assert_eq!(
+            parse(&args("exists Cargo.toml src"))?,
+            vec![
+                Requirement::Exists("Cargo.toml".into()),
+                Requirement::Exists("src".into())
+            ]
+        );
+        assert_eq!(
+            parse(&args("tmplr >=0.0.9"))?,
+            vec![Requirement::Version("0.0.9".into())]
+        );
+        assert!(parse(&args("var a b c")).is_err());
+        assert!(parse(&args("exists")).is_err());
+        assert!(parse(&args("tmplr latest")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(compare_versions(&[0, 0, 10], &[0, 0, 9]), Ordering::Greater);
+        assert_eq!(compare_versions(&[1, 2], &[1, 2, 0]), Ordering::Equal);
+        assert_eq!(compare_versions(&[0, 9], &[0, 10]), Ordering::Less);
+    }
+
+    #[test]
+    fn matches_variable_values() {
+        let vars = HashMap::from([("license".to_string(), "MIT".to_string())]);
+        let var = |name: &str, value: Option<&str>| Requirement::Var {
+            name: name.into(),
+            value: value.map(String::from),
+        };
+        let check_var = |requirement: &Requirement, vars| check(requirement, vars, Path::new("."));
+        assert_eq!(check_var(&var("license", Some("MIT")), &vars), None);
+        assert_eq!(
+            check_var(&var("license", Some("/MIT|Apache-2.0/")), &vars),
+            None
+        );
+        assert!(check_var(&var("license", Some("/M/")), &vars).is_some());
+        assert!(check_var(&var("author", None), &vars).is_some());
+    }
+}
@@ -1,39 +1,271 @@
 use std::{
-    env::{self, current_dir},
+    env,
     fmt::Write,
     fs::{self},
     io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     str::FromStr,
 };
 
+use crate::dir_template;
 use crate::error_handling::quit_with_error;
+use crate::header::{Attributes, Header, parse_header, quote};
+use crate::literate;
+use crate::require;
+use crate::txtar;
 use crate::{error_handling::OkOrIoOther, list_templates::list_templates_relative};
 
 pub const EXTENSION: &str = "tmplr";
 pub const OPEN: &str = "{###";
 pub const CLOSE: &str = "###}";
 
+pub const BOM: char = '\u{feff}';
+
+pub const VAR_OPEN: &str = "{{";
+pub const VAR_CLOSE: &str = "}}";
+pub const COMMENT_OPEN: &str = "{#-";
+pub const COMMENT_CLOSE: &str = "-#}";
+
+pub const FILE_ATTRIBUTES: &[&str] = &[
+    "if",
+    "mode",
+    "delims",
+    "trim",
+    "final_newline",
+    "eol",
+    "bom",
+    "once",
+];
+pub const EXT_ATTRIBUTES: &[&str] = &["if", "delims", "trim", "final_newline", "eol"];
+pub const PREPEND_ATTRIBUTES: &[&str] = &["if", "delims", "trim", "final_newline", "eol"];
+pub const PATCH_ATTRIBUTES: &[&str] = &["if", "delims", "fuzz"];
+pub const BLOCK_ATTRIBUTES: &[&str] = &[
+    "if",
+    "delims",
+    "id",
+    "comment",
+    "trim",
+    "final_newline",
+    "eol",
+];
+pub const MERGE_ATTRIBUTES: &[&str] = &["if", "delims", "format", "conflict"];
+pub const INSERT_ATTRIBUTES: &[&str] = &[
+    "if",
+    "delims",
+    "trim",
+    "final_newline",
+    "eol",
+    "after",
+    "before",
+    "at",
+];
+pub const DIR_ATTRIBUTES: &[&str] = &["if", "mode"];
+pub const REMOVE_ATTRIBUTES: &[&str] = &["if", "delims"];
+pub const MOVE_ATTRIBUTES: &[&str] = &["if", "delims"];
+pub const RUN_ATTRIBUTES: &[&str] = &["if", "delims"];
+pub const REQUIRE_ATTRIBUTES: &[&str] = &["if", "delims", "message"];
+pub const VAR_ATTRIBUTES: &[&str] = &["delims", "default", "prompt", "choices", "secret"];
+pub const DELIMITERS_ATTRIBUTES: &[&str] = &["sections", "comments"];
+
+/// Opening and closing markers, for `{{ variables }}`, `{### SECTIONS ###}`
+/// or `{#- comments -#}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delimiters {
+    pub open: String,
+    pub close: String,
+}
+
+impl Delimiters {
+    pub fn variables() -> Self {
+        Delimiters {
+            open: VAR_OPEN.into(),
+            close: VAR_CLOSE.into(),
+        }
+    }
+
+    pub fn sections() -> Self {
+        Delimiters {
+            open: OPEN.into(),
+            close: CLOSE.into(),
+        }
+    }
+
+    pub fn comments() -> Self {
+        Delimiters {
+            open: COMMENT_OPEN.into(),
+            close: COMMENT_CLOSE.into(),
+        }
+    }
+
+    pub fn new(open: &str, close: &str) -> io::Result<Self> {
+        if open.is_empty() || close.is_empty() || open.contains(char::is_whitespace) {
+            return err(&format!("Invalid delimiters: `{}` `{}`", open, close));
+        }
+        Ok(Delimiters {
+            open: open.into(),
+            close: close.into(),
+        })
+    }
+
+    /// Parses delimiters written as `"OPEN CLOSE"`, e.g. `"[[ ]]"`.
+    pub fn parse(value: &str) -> io::Result<Self> {
+        match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [open, close] => Delimiters::new(open, close),
+            _ => err(&format!(
+                "Delimiters should be given as \"OPEN CLOSE\", got: {}",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Delimiters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.open, self.close)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Node {
-    Dir(PathBuf),
-    File { path: String, content: String },
-    Ext { path: String, content: String },
+    Dir {
+        path: PathBuf,
+        attrs: Attributes,
+    },
+    File {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Ext {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Prepend {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Insert {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Patch {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Merge {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Block {
+        path: String,
+        content: String,
+        attrs: Attributes,
+    },
+    Remove {
+        path: String,
+        attrs: Attributes,
+    },
+    Move {
+        from: String,
+        to: String,
+        attrs: Attributes,
+    },
+    Run {
+        content: String,
+        attrs: Attributes,
+    },
+    Require {
+        args: Vec<String>,
+        attrs: Attributes,
+    },
+    Var {
+        name: String,
+        attrs: Attributes,
+    },
 }
 type Template = Vec<Node>;
 
+impl Node {
+    pub fn attrs(&self) -> &Attributes {
+        match self {
+            Node::Dir { attrs, .. }
+            | Node::File { attrs, .. }
+            | Node::Ext { attrs, .. }
+            | Node::Prepend { attrs, .. }
+            | Node::Insert { attrs, .. }
+            | Node::Patch { attrs, .. }
+            | Node::Merge { attrs, .. }
+            | Node::Block { attrs, .. }
+            | Node::Remove { attrs, .. }
+            | Node::Move { attrs, .. }
+            | Node::Run { attrs, .. }
+            | Node::Require { attrs, .. }
+            | Node::Var { attrs, .. } => attrs,
+        }
+    }
+
+    pub fn attrs_mut(&mut self) -> &mut Attributes {
+        match self {
+            Node::Dir { attrs, .. }
+            | Node::File { attrs, .. }
+            | Node::Ext { attrs, .. }
+            | Node::Prepend { attrs, .. }
+            | Node::Insert { attrs, .. }
+            | Node::Patch { attrs, .. }
+            | Node::Merge { attrs, .. }
+            | Node::Block { attrs, .. }
+            | Node::Remove { attrs, .. }
+            | Node::Move { attrs, .. }
+            | Node::Run { attrs, .. }
+            | Node::Require { attrs, .. }
+            | Node::Var { attrs, .. } => attrs,
+        }
+    }
+
+    /// Section body, `None` for sections which don't take content.
+    pub fn content_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Node::File { content, .. }
+            | Node::Ext { content, .. }
+            | Node::Prepend { content, .. }
+            | Node::Insert { content, .. }
+            | Node::Patch { content, .. }
+            | Node::Merge { content, .. }
+            | Node::Block { content, .. }
+            | Node::Run { content, .. } => Some(content),
+            Node::Dir { .. }
+            | Node::Remove { .. }
+            | Node::Move { .. }
+            | Node::Require { .. }
+            | Node::Var { .. } => None,
+        }
+    }
+}
+
 pub fn read_template(path: &Path) -> io::Result<Template> {
+    let file_string = get_template_string_from_path(path)?;
+    parse_template(&file_string)
+}
+
+pub fn parse_template(file_string: &str) -> io::Result<Template> {
+    let file_string = file_string.strip_prefix(BOM).unwrap_or(file_string);
     let mut result: Template = Vec::new();
+    let mut sections = Delimiters::sections();
+    let mut variables = Delimiters::variables();
+    let mut comments = Delimiters::comments();
     let mut cursor = 0;
     let mut current_node: Option<Node> = None;
 
-    fn push_output(s: &str, current_node: &mut Option<Node>) {
-        match current_node {
+    fn push_output(s: &str, comments: &Delimiters, current_node: &mut Option<Node>) {
+        match current_node.as_mut().map(Node::content_mut) {
             None => (),
-            Some(Node::File { content, .. }) | Some(Node::Ext { content, .. }) => {
-                content.push_str(s)
-            }
-            Some(Node::Dir { .. }) => quit_with_error(256, "Dir node shouldn't be current one"),
+            Some(Some(content)) => content.push_str(&strip_comments(s, comments)),
+            Some(None) => quit_with_error(256, "Section without body shouldn't be current one"),
         }
     }
 
@@ -44,79 +276,412 @@ pub fn read_template(path: &Path) -> io::Result<Template> {
         }
     }
 
-    let file_string = get_template_string_from_path(path)?;
+    // Nodes keep their own delimiters, so template-wide ones are stored in attributes
+    fn section_attrs(
+        header: &Header,
+        known: &[&str],
+        variables: &Delimiters,
+    ) -> io::Result<Attributes> {
+        header.attrs.warn_unknown(&header.cmd, known);
+        let mut attrs = header.attrs.clone();
+        match attrs.get("delims") {
+            Some(delims) => _ = Delimiters::parse(delims)?,
+            None if *variables != Delimiters::variables() => {
+                attrs.insert("delims", &variables.to_string())
+            }
+            None => (),
+        }
+        Ok(attrs)
+    }
 
-    while let Some(start_offset) = file_string[cursor..].find(OPEN) {
+    while let Some(start_offset) = file_string[cursor..].find(&sections.open) {
         let tag_start = cursor + start_offset;
 
-        push_output(&file_string[cursor..tag_start], &mut current_node);
-        let content_start = tag_start + OPEN.len();
+        push_output(
+            &file_string[cursor..tag_start],
+            &comments,
+            &mut current_node,
+        );
+        let content_start = tag_start + sections.open.len();
         let remaining = &file_string[content_start..];
 
-        if let Some(end_offset) = remaining.find(CLOSE) {
-            let inner = &remaining[..end_offset].trim();
-            // process cmds
-            let (cmd, params) = match inner.split_once(char::is_whitespace) {
-                Some((c, p)) => (c.trim(), p),
-                None => (*inner, ""),
+        if let Some(end_offset) = find_header_close(remaining, &sections.close) {
+            let header_end = content_start + end_offset + sections.close.len();
+            if remaining[..end_offset].trim_start().starts_with('#') {
+                // `{### # note ###}` is dropped together with its line when
+                // it stands alone, and doesn't end current section
+                let line_start = file_string[..tag_start].rfind('\n').map_or(0, |n| n + 1);
+                let own_line = file_string[line_start..tag_start].trim().is_empty();
+                cursor = header_end;
+                if own_line {
+                    if let Some(Some(content)) = current_node.as_mut().map(Node::content_mut) {
+                        content.truncate(content.trim_end_matches([' ', '\t']).len());
+                    }
+                    cursor = skip_header_line_end(file_string, header_end);
+                }
+                continue;
+            }
+            let header = parse_header(&remaining[..end_offset], &variables)?;
+            let cmd = header.cmd.as_str();
+            let path_arg = || -> io::Result<&str> {
+                if header.args.len() > 1 {
+                    eprintln!(
+                        "WARN: Unexpected arguments in {} section: {} (quote paths containing spaces)",
+                        cmd,
+                        header.args[1..].join(" ")
+                    );
+                }
+                header
+                    .args
+                    .first()
+                    .map(String::as_str)
+                    .ok_or_ioerror(format!("Missing path in {} section", cmd))
             };
 
             match cmd.to_uppercase().as_str() {
                 "DIR" => {
                     push_current_node(&mut current_node, &mut result);
-                    let file_path = validate_path_string(params)?;
-                    let new_dir = Node::Dir(file_path);
+                    let attrs = section_attrs(&header, DIR_ATTRIBUTES, &variables)?;
+                    let file_path = section_path(path_arg()?)?;
+                    let new_dir = Node::Dir {
+                        path: file_path,
+                        attrs,
+                    };
                     result.push(new_dir);
                 }
-                "FILE" => {
+                "FILE" | "EXT" | "PREPEND" | "INSERT" | "PATCH" | "MERGE" | "BLOCK" => {
                     push_current_node(&mut current_node, &mut result);
-                    if let Ok(path) = validate_path_string(params) {
-                        let file_path = path
-                            .to_str()
-                            .ok_or_ioerror("Can't convert FILE path to string")?;
-                        current_node = Some(Node::File {
-                            path: file_path.into(),
-                            content: String::new(),
-                        });
+                    let kind = cmd.to_uppercase();
+                    let known = match kind.as_str() {
+                        "FILE" => FILE_ATTRIBUTES,
+                        "EXT" => EXT_ATTRIBUTES,
+                        "PREPEND" => PREPEND_ATTRIBUTES,
+                        "PATCH" => PATCH_ATTRIBUTES,
+                        "MERGE" => MERGE_ATTRIBUTES,
+                        "BLOCK" => BLOCK_ATTRIBUTES,
+                        _ => INSERT_ATTRIBUTES,
                     };
+                    let attrs = section_attrs(&header, known, &variables)?;
+                    if kind == "INSERT"
+                        && attrs.get("after").is_none()
+                        && attrs.get("before").is_none()
+                    {
+                        return err("INSERT section needs `after` or `before` attribute");
+                    }
+                    if kind == "BLOCK" && attrs.get("id").is_none_or(str::is_empty) {
+                        return err("BLOCK section needs `id` attribute");
+                    }
+                    let path = path_string(section_path(path_arg()?)?)?;
+                    let content = String::new();
+                    current_node = Some(match kind.as_str() {
+                        "FILE" => Node::File {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        "EXT" => Node::Ext {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        "PREPEND" => Node::Prepend {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        "PATCH" => Node::Patch {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        "MERGE" => Node::Merge {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        "BLOCK" => Node::Block {
+                            path,
+                            content,
+                            attrs,
+                        },
+                        _ => Node::Insert {
+                            path,
+                            content,
+                            attrs,
+                        },
+                    });
+                }
+                "REMOVE" => {
+                    push_current_node(&mut current_node, &mut result);
+                    let attrs = section_attrs(&header, REMOVE_ATTRIBUTES, &variables)?;
+                    let path = path_string(section_path(path_arg()?)?)?;
+                    result.push(Node::Remove { path, attrs });
+                }
+                "MOVE" => {
+                    push_current_node(&mut current_node, &mut result);
+                    let attrs = section_attrs(&header, MOVE_ATTRIBUTES, &variables)?;
+                    let (from, to) = match header.args.as_slice() {
+                        [from, arrow, to] if arrow == "->" => (from, to),
+                        [from, to] => (from, to),
+                        _ => return err("MOVE section expects `SOURCE -> TARGET` paths"),
+                    };
+                    result.push(Node::Move {
+                        from: path_string(section_path(from)?)?,
+                        to: path_string(section_path(to)?)?,
+                        attrs,
+                    });
+                }
+                "RUN" => {
+                    push_current_node(&mut current_node, &mut result);
+                    let attrs = section_attrs(&header, RUN_ATTRIBUTES, &variables)?;
+                    if !header.args.is_empty() {
+                        eprintln!(
+                            "WARN: RUN section takes commands in its body, ignoring: {}",
+                            header.args.join(" ")
+                        );
+                    }
+                    current_node = Some(Node::Run {
+                        content: String::new(),
+                        attrs,
+                    });
+                }
+                "REQUIRE" => {
+                    push_current_node(&mut current_node, &mut result);
+                    let attrs = section_attrs(&header, REQUIRE_ATTRIBUTES, &variables)?;
+                    // Arguments are parsed again once variables are rendered
+                    require::parse(&header.args)?;
+                    result.push(Node::Require {
+                        args: header.args.clone(),
+                        attrs,
+                    });
                 }
-                "EXT" => {
+                "VAR" => {
                     push_current_node(&mut current_node, &mut result);
-                    if let Ok(path) = validate_path_string(params) {
-                        let file_path = path
-                            .to_str()
-                            .ok_or_ioerror("Can't convert EXT path to string")?;
-                        current_node = Some(Node::Ext {
-                            path: file_path.into(),
-                            content: String::new(),
-                        });
+                    let attrs = section_attrs(&header, VAR_ATTRIBUTES, &variables)?;
+                    let [name] = header.args.as_slice() else {
+                        return err("VAR section expects a single variable name");
                     };
+                    result.push(Node::Var {
+                        name: name.clone(),
+                        attrs,
+                    });
+                }
+                "META" => {
+                    // Read by `meta` module, doesn't produce output
+                    push_current_node(&mut current_node, &mut result);
+                }
+                "DELIMITERS" => {
+                    let close_len = sections.close.len();
+                    header.attrs.warn_unknown(cmd, DELIMITERS_ATTRIBUTES);
+                    match header.args.as_slice() {
+                        [] => (),
+                        [open, close] => variables = Delimiters::new(open, close)?,
+                        _ => return err("DELIMITERS expects variable OPEN and CLOSE markers"),
+                    }
+                    if let Some(new_sections) = header.attrs.get("sections") {
+                        sections = Delimiters::parse(new_sections)?;
+                    }
+                    if let Some(new_comments) = header.attrs.get("comments") {
+                        comments = Delimiters::parse(new_comments)?;
+                    }
+                    cursor =
+                        skip_header_line_end(file_string, content_start + end_offset + close_len);
+                    continue;
                 }
                 _ => {
                     eprintln!("Unknown command: {}", cmd);
                 }
             }
-            cursor = content_start + end_offset + CLOSE.len();
+            cursor = skip_header_line_end(
+                file_string,
+                content_start + end_offset + sections.close.len(),
+            );
         } else {
-            push_output(&file_string[tag_start..], &mut current_node);
+            push_output(&file_string[tag_start..], &comments, &mut current_node);
             cursor = file_string.len();
             break;
         }
     }
-    push_output(&file_string[cursor..], &mut current_node);
+    push_output(&file_string[cursor..], &comments, &mut current_node);
     if let Some(node) = current_node {
         result.push(node);
     }
     Ok(result)
 }
 
+/// Removes `{#- ... -#}` comments from section body. Lines holding nothing
+/// but a comment are removed entirely; unterminated comments are kept.
+fn strip_comments(text: &str, comments: &Delimiters) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some(start) = text[cursor..].find(&comments.open).map(|n| cursor + n) {
+        let Some(end) = text[start..]
+            .find(&comments.close)
+            .map(|n| start + n + comments.close.len())
+        else {
+            break;
+        };
+        let line_start = text[..start].rfind('\n').map_or(0, |n| n + 1);
+        let rest = &text[end..];
+        let line_end = rest.find('\n').map_or(text.len(), |n| end + n + 1);
+        let own_line =
+            text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty();
+        match own_line {
+            true => {
+                result.push_str(&text[cursor..line_start]);
+                cursor = line_end;
+            }
+            false => {
+                result.push_str(&text[cursor..start]);
+                cursor = end;
+            }
+        }
+    }
+    result.push_str(&text[cursor..]);
+    result
+}
+
+/// Offset of section close marker, skipping `"` quoted values, so that
+/// `sections="{#### ####}"` doesn't end the header early. Falls back to the
+/// first marker when quotes aren't balanced.
+fn find_header_close(remaining: &str, close: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut chars = remaining.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '\\' if quoted => _ = chars.next(),
+            '"' => quoted = !quoted,
+            '\n' => break,
+            _ if !quoted && remaining[pos..].starts_with(close) => return Some(pos),
+            _ => (),
+        }
+    }
+    remaining.find(close)
+}
+
+/// Section body starts on the line after its header, so whitespace and the
+/// newline ending the header line are not part of the content.
+fn skip_header_line_end(file_string: &str, cursor: usize) -> usize {
+    let rest = &file_string[cursor..];
+    let line_end = rest.find('\n').map(|n| n + 1).unwrap_or(rest.len());
+    if rest[..line_end].trim().is_empty() {
+        cursor + line_end
+    } else {
+        cursor
+    }
+}
+
 pub fn get_template_string_from_path(path: &Path) -> io::Result<String> {
-    fs::read_to_string(path)
-        .or_else(|_| fs::read_to_string(get_config_dir().join(path)))
+    read_template_string(path)
+        .or_else(|_| read_template_string(&get_config_dir().join(path)))
         .or_else(|_| fs::read_to_string(get_config_dir().join(path).with_added_extension("tmplr")))
         .or_else(|_| read_partial_matched_template(path))
 }
+
+/// Writes `.tmplr` text with `DIR` and `FILE` sections after `preamble`.
+/// Section and comment markers are changed when some file content contains
+/// them, e.g. `{#-` of Jinja comments.
+pub fn write_template(preamble: &str, dirs: &[String], files: &[(String, String)]) -> String {
+    write_template_with_attrs(preamble, dirs, files, &[])
+}
+
+/// Like `write_template`, `attrs[i]` are written into header of `files[i]`.
+pub fn write_template_with_attrs(
+    preamble: &str,
+    dirs: &[String],
+    files: &[(String, String)],
+    attrs: &[Attributes],
+) -> String {
+    let mut result = String::from(preamble);
+    let mut level = 3;
+    let (open, close) = loop {
+        let open = format!("{{{}", "#".repeat(level));
+        let close = format!("{}}}", "#".repeat(level));
+        if !files.iter().any(|(_, content)| content.contains(&open)) {
+            break (open, close);
+        }
+        level += 1;
+    };
+    let mut dashes = 1;
+    let comments = loop {
+        let open = format!("{{#{}", "-".repeat(dashes));
+        if !files.iter().any(|(_, content)| content.contains(&open)) {
+            break Delimiters {
+                close: format!("{}#}}", "-".repeat(dashes)),
+                open,
+            };
+        }
+        dashes += 1;
+    };
+    let mut changed = Vec::new();
+    if open != OPEN {
+        changed.push(format!("sections=\"{} {}\"", open, close));
+    }
+    if comments != Delimiters::comments() {
+        changed.push(format!("comments=\"{}\"", comments));
+    }
+    if !changed.is_empty() {
+        let _ = writeln!(
+            result,
+            "{} DELIMITERS {} {}",
+            OPEN,
+            changed.join(" "),
+            CLOSE
+        );
+    }
+    for path in dirs {
+        let _ = writeln!(result, "{} DIR {} {}", open, quote(path), close);
+    }
+    for (i, (path, content)) in files.iter().enumerate() {
+        match attrs.get(i).filter(|a| !a.is_empty()) {
+            Some(attrs) => {
+                let _ = writeln!(result, "{} FILE {} {} {}", open, quote(path), attrs, close);
+            }
+            None => {
+                let _ = writeln!(result, "{} FILE {} {}", open, quote(path), close);
+            }
+        }
+        result.push_str(content);
+    }
+    result
+}
+
+/// Reads `.tmplr` file, or directory template, `.txtar` archive and Markdown
+/// as equivalent `.tmplr` text.
+pub fn read_template_string(path: &Path) -> io::Result<String> {
+    if path.is_dir() {
+        return dir_template::read(path);
+    }
+    let text = fs::read_to_string(path)?;
+    if txtar::is_txtar(path) {
+        return Ok(txtar::to_template(&text));
+    }
+    match literate::is_markdown(path) {
+        true => Ok(literate::to_template(&text)),
+        false => Ok(text),
+    }
+}
 pub(crate) fn read_partial_matched_template(path: &Path) -> io::Result<String> {
+    read_template_string(&get_config_dir().join(partial_match(path)?))
+}
+
+/// Path `get_template_string_from_path` reads template `path` from, e.g.
+/// `~/.config/tmplr/rust/cli.tmplr` for partially matched `cli`.
+pub fn resolve_template_path(path: &Path) -> io::Result<PathBuf> {
+    let config_dir = get_config_dir();
+    let candidates = [
+        path.to_path_buf(),
+        config_dir.join(path),
+        config_dir.join(path).with_added_extension(EXTENSION),
+    ];
+    match candidates.into_iter().find(|candidate| candidate.exists()) {
+        Some(found) => Ok(found),
+        None => Ok(config_dir.join(partial_match(path)?)),
+    }
+}
+
+/// The only library template whose path contains `path`.
+fn partial_match(path: &Path) -> io::Result<String> {
     let input_path = path.to_string_lossy().to_string();
     let config_dir = get_config_dir();
     let all_templates = list_templates_relative(&config_dir);
@@ -139,27 +704,125 @@ pub(crate) fn read_partial_matched_template(path: &Path) -> io::Result<String> {
         .pop()
         .ok_or_else(|| io::Error::other("Template not found"))?;
     println!("Expanding: {}", m);
-    fs::read_to_string(config_dir.join(m))
+    Ok(m)
 }
 
-pub fn validate_path_string(str_path: &str) -> io::Result<PathBuf> {
-    let curdir = current_dir().map_err(|_| other_err("Can't get current dir"))?;
+/// Validates rendered output path against the output directory `root`.
+pub fn validate_path_string(root: &Path, str_path: &str) -> io::Result<PathBuf> {
     let pathbuf_result = PathBuf::from_str(str_path);
     let pathbuf: PathBuf = pathbuf_result.map_err(|_| other_err("Not a path"))?;
-    let path = pathbuf.as_path();
-    validate_path(curdir.as_path(), path)
+    validate_path(root, pathbuf.as_path())
+}
+
+/// Path of a section header. Only its form is checked here, where it leads
+/// is checked against the output directory once variables are filled in.
+fn section_path(str_path: &str) -> io::Result<PathBuf> {
+    let pathbuf = PathBuf::from_str(str_path).map_err(|_| other_err("Not a path"))?;
+    check_components(&pathbuf)?;
+    Ok(pathbuf)
+}
+
+fn path_string(path: PathBuf) -> io::Result<String> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|p| other_err(&format!("Can't convert {} path to string", p.display())))
+}
+
+/// Names Windows reserves for devices, with or without extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks the path is relative, without `..`, NUL characters, reserved names
+/// or `.git` directories.
+fn check_components(relative_path: &Path) -> io::Result<()> {
+    let invalid = |reason: &str| {
+        err(&format!(
+            "Invalid path `{}`: {}",
+            relative_path.display(),
+            reason
+        ))
+    };
+    if relative_path.as_os_str().is_empty() {
+        return invalid("path is empty");
+    }
+    if relative_path.as_os_str().as_encoded_bytes().contains(&0) {
+        return invalid("contains NUL character");
+    }
+    for component in relative_path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                return invalid("absolute paths aren't allowed");
+            }
+            Component::ParentDir => return invalid("target reaches outside parent directory"),
+            Component::CurDir => (),
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                let stem = name.split('.').next().unwrap_or_default().trim_end();
+                if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+                    return invalid(&format!("`{}` is a reserved name", name));
+                }
+                if name.ends_with(['.', ' ']) && name != "." {
+                    return invalid(&format!("`{}` ends with a dot or space", name));
+                }
+                if name.eq_ignore_ascii_case(".git") {
+                    return invalid("writing into `.git` isn't allowed");
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
+/// Checks that template output path stays inside `target_root`: it has to be
+/// relative, without `..`, NUL characters, reserved names or `.git`
+/// directories, and existing symlinks on the way can't lead outside the root.
 pub fn validate_path(target_root: &Path, relative_path: &Path) -> io::Result<PathBuf> {
-    let joined = target_root.join(relative_path);
-    //let canonical_root = joined.canonicalize().map_err(|e| e.to_string())?;
-
-    if joined
-        .components()
-        .any(|c| matches!(c, std::path::Component::ParentDir))
-    {
-        return err("Target reaches outside parent directory");
+    check_components(relative_path)?;
+    let invalid = |reason: &str| {
+        err(&format!(
+            "Invalid path `{}`: {}",
+            relative_path.display(),
+            reason
+        ))
+    };
+
+    // Existing parts of the path are resolved, so symlinks can't escape
+    let Ok(root) = target_root.canonicalize() else {
+        return Ok(relative_path.to_path_buf());
     };
+    let mut current = root.clone();
+    for component in relative_path.components() {
+        current.push(component);
+        if current.symlink_metadata().is_err() {
+            break;
+        }
+        match current.canonicalize() {
+            Ok(resolved) if resolved.starts_with(&root) => current = resolved,
+            Ok(resolved) => {
+                return invalid(&format!(
+                    "symlink leads outside of {} (to {})",
+                    root.display(),
+                    resolved.display()
+                ));
+            }
+            // Dangling symlink, its target is checked as if it was the path
+            Err(_) => match fs::read_link(&current) {
+                Ok(target)
+                    if target.is_absolute()
+                        || target.components().any(|c| c == Component::ParentDir) =>
+                {
+                    return invalid(&format!(
+                        "symlink leads outside of {} (to {})",
+                        root.display(),
+                        target.display()
+                    ));
+                }
+                _ => break,
+            },
+        }
+    }
 
     Ok(relative_path.to_path_buf())
 }
@@ -179,6 +842,17 @@ fn get_config_dir() -> PathBuf {
 pub fn templates_dir() -> PathBuf {
     get_config_dir()
 }
+
+/// Whether template comes from user's own templates directory. Paths which
+/// don't exist as given were resolved against that directory.
+pub fn is_in_library(path: &Path) -> bool {
+    match path.canonicalize() {
+        Ok(path) => templates_dir()
+            .canonicalize()
+            .is_ok_and(|dir| path.starts_with(dir)),
+        Err(_) => true,
+    }
+}
 fn err<T>(err: &str) -> io::Result<T> {
     Err(io::Error::other(err))
 }
@@ -187,4 +861,82 @@ fn other_err(err: &str) -> io::Error {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn contents(template: &Template) -> Vec<String> {
+        template
+            .iter()
+            .cloned()
+            .filter_map(|mut node| node.content_mut().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn section_body_is_exact() -> io::Result<()> {
+        let template = parse_template(
+            "preamble\n{### FILE a ###}\n\n  a\n\n{### EXT b ###}   \nb{### FILE c ###} c\n",
+        )?;
+        assert_eq!(contents(&template), vec!["\n  a\n\n", "b", " c\n"]);
+        Ok(())
+    }
+
+    #[test]
+    fn escalated_section_markers_round_trip() -> io::Result<()> {
+        let files = [("a.txt".to_string(), "{### FILE b ###}\n".to_string())];
+        let template = parse_template(&write_template("", &[], &files))?;
+        assert_eq!(contents(&template), vec!["{### FILE b ###}\n"]);
+        Ok(())
+    }
+
+    #[test]
+    fn comments_are_stripped() -> io::Result<()> {
+        let template = parse_template(
+            "{### FILE a ###}\na {#- inline -#}b\n  {#- own line -#}\n{### # note ###}\nc\n{#- multi\nline -#}\nd {### # end ###}\n{#- open\n",
+        )?;
+        assert_eq!(contents(&template), vec!["a b\nc\nd \n{#- open\n"]);
+        Ok(())
+    }
+
+    #[test]
+    fn jinja_comments_round_trip() -> io::Result<()> {
+        let jinja = "{#- header -#}\n{%- for x in xs %}{{ x }}{% endfor %}\n";
+        let files = [("base.j2".to_string(), jinja.to_string())];
+        let text = write_template("", &[], &files);
+        assert!(text.starts_with("{### DELIMITERS comments=\"{#-- --#}\" ###}\n"));
+        assert_eq!(contents(&parse_template(&text)?), vec![jinja]);
+        Ok(())
+    }
+
+    #[test]
+    fn validate_path_rejects_escapes() -> Result<(), Box<dyn std::error::Error>> {
+        let root = assert_fs::TempDir::new()?;
+        let rejected = |path: &str| {
+            validate_path(root.path(), Path::new(path))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(rejected("/etc/passwd").contains("absolute paths aren't allowed"));
+        assert!(rejected("a/../../b").contains("outside parent directory"));
+        assert!(rejected("a\0b").contains("NUL"));
+        assert!(rejected("src/con.rs").contains("`con.rs` is a reserved name"));
+        assert!(rejected("notes. ").contains("ends with a dot or space"));
+        assert!(rejected(".git/hooks/pre-commit").contains("`.git`"));
+        assert_eq!(
+            validate_path(root.path(), Path::new("src/./main.rs"))?,
+            PathBuf::from("src/./main.rs")
+        );
+
+        #[cfg(unix)]
+        {
+            let outside = assert_fs::TempDir::new()?;
+            std::os::unix::fs::symlink(outside.path(), root.path().join("link"))?;
+            std::os::unix::fs::symlink("../../x", root.path().join("dangling"))?;
+            std::os::unix::fs::symlink(root.path(), root.path().join("inside"))?;
+            assert!(rejected("link/file.txt").contains("symlink leads outside"));
+            assert!(rejected("dangling").contains("symlink leads outside"));
+            assert!(validate_path(root.path(), Path::new("inside/file.txt")).is_ok());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::patch;
+
+pub const LOCAL_MARKER: &str = "<<<<<<< local";
+pub const SEPARATOR: &str = "=======";
+pub const TEMPLATE_MARKER: &str = ">>>>>>> template";
+
+/// Merged content, regions changed on both sides are between conflict
+/// markers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Merged {
+    pub content: String,
+    pub conflicts: usize,
+}
+
+impl Merged {
+    /// Takes side which changed `base`, or marks conflict when both did.
+    fn push_region(&mut self, base: &[&str], local: &[&str], template: &[&str]) {
+        if local == template || template == base {
+            self.content.extend(local.iter().copied());
+        } else if local == base {
+            self.content.extend(template.iter().copied());
+        } else {
+            self.conflicts += 1;
+            for (marker, lines) in [(LOCAL_MARKER, local), (SEPARATOR, template)] {
+                self.content.push_str(marker);
+                self.content.push('\n');
+                self.push_lines(lines);
+            }
+            self.content.push_str(TEMPLATE_MARKER);
+            self.content.push('\n');
+        }
+    }
+
+    /// Pushes lines of a conflict, ending the last one so the marker after
+    /// it is on its own line.
+    fn push_lines(&mut self, lines: &[&str]) {
+        self.content.extend(lines.iter().copied());
+        if !lines.is_empty() && !self.content.ends_with('\n') {
+            self.content.push('\n');
+        }
+    }
+}
+
+/// Index in `other` of each line of `base` kept there.
+fn kept_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut kept = vec![None; base.len()];
+    for (i, j) in patch::common_lines(base, other) {
+        kept[i] = Some(j);
+    }
+    kept
+}
+
+/// Line based three-way merge of `local` and `template`, which both come
+/// from `base`. Lines kept on both sides split the files into regions,
+/// each region is taken from the side which changed it.
+pub fn merge(base: &str, local: &str, template: &str) -> Merged {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let local: Vec<&str> = local.split_inclusive('\n').collect();
+    let template: Vec<&str> = template.split_inclusive('\n').collect();
+    let kept_local = kept_lines(&base, &local);
+    let kept_template = kept_lines(&base, &template);
+
+    let mut merged = Merged::default();
+    let (mut b, mut l, mut t) = (0, 0, 0);
+    loop {
+        let stable = (b..base.len()).find_map(|i| Some((i, kept_local[i]?, kept_template[i]?)));
+        let (next_b, next_l, next_t) = stable.unwrap_or((base.len(), local.len(), template.len()));
+        merged.push_region(&base[b..next_b], &local[l..next_l], &template[t..next_t]);
+        if stable.is_none() {
+            return merged;
+        }
+        merged.content.push_str(base[next_b]);
+        (b, l, t) = (next_b + 1, next_l + 1, next_t + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_changes_from_both_sides() {
+        let base = "a\nb\nc\nd\ne\n";
+        let local = "a\nB\nc\nd\ne\n";
+        let template = "a\nb\nc\nd\nE\nf\n";
+        let merged = merge(base, local, template);
+        assert_eq!(merged.content, "a\nB\nc\nd\nE\nf\n");
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(merge(base, base, template).content, template);
+        assert_eq!(merge(base, local, base).content, local);
+    }
+
+    #[test]
+    fn marks_conflicts() {
+        let merged = merge("a\nb\nc\n", "a\nlocal\nc\n", "a\ntemplate\nc\n");
+        assert_eq!(
+            merged.content,
+            "a\n<<<<<<< local\nlocal\n=======\ntemplate\n>>>>>>> template\nc\n"
+        );
+        assert_eq!(merged.conflicts, 1);
+    }
+}
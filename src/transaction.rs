@@ -0,0 +1,551 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Once,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+static COMMITTING: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Staged state of a path, shadowing what's on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    File(String),
+    /// Directory which didn't exist, nothing on disk is inside.
+    NewDir,
+    Missing,
+    /// Moved here from the disk path.
+    Moved(PathBuf),
+}
+
+#[derive(Debug)]
+enum Change {
+    Write {
+        path: PathBuf,
+        content: String,
+        mode: Option<u32>,
+    },
+    CreateDir {
+        path: PathBuf,
+        mode: Option<u32>,
+    },
+    Remove {
+        path: PathBuf,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+/// What a path is, as seen through staged changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+}
+
+/// Changes to the target directory collected before anything is written.
+/// Reads go through staged changes, so sections see files as left by the
+/// sections before them. `commit` applies everything or nothing.
+#[derive(Debug)]
+pub struct Transaction {
+    root: PathBuf,
+    changes: Vec<Change>,
+    staged: BTreeMap<PathBuf, Entry>,
+    preview: bool,
+    /// What `FILE` sections generated, before local changes were kept.
+    generated: BTreeMap<PathBuf, String>,
+}
+
+/// Undo step of a committed change.
+#[derive(Debug)]
+enum Undo {
+    Remove(PathBuf),
+    RemoveDir(PathBuf),
+    /// Puts back what was moved away, `(from, to)`.
+    Rename(PathBuf, PathBuf),
+    Permissions(PathBuf, fs::Permissions),
+}
+
+/// Drops `.` components, so the same path is always the same key.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+fn not_a_directory(path: &Path) -> io::Error {
+    io::Error::other(format!("{} is not a directory", path.display()))
+}
+
+impl Transaction {
+    pub fn new(root: &Path) -> Self {
+        Transaction {
+            root: root.to_path_buf(),
+            changes: Vec::new(),
+            staged: BTreeMap::new(),
+            preview: false,
+            generated: BTreeMap::new(),
+        }
+    }
+
+    /// Transaction which is only looked at, without progress messages.
+    pub fn preview(root: &Path) -> Self {
+        Transaction {
+            preview: true,
+            ..Transaction::new(root)
+        }
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.preview
+    }
+
+    /// Prints progress message, unless previewing.
+    pub fn note(&self, message: &str) {
+        if !self.preview {
+            println!("{}", message);
+        }
+    }
+
+    /// Paths touched by staged changes, in order of the first change.
+    pub fn touched(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for change in &self.changes {
+            let changed = match change {
+                Change::Write { path, .. }
+                | Change::CreateDir { path, .. }
+                | Change::Remove { path } => vec![path],
+                Change::Move { from, to } => vec![from, to],
+            };
+            for path in changed.into_iter().map(|path| normalize(path)) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    /// Output directory, paths of changes are relative to it.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Records template output for `path`, which may differ from what ends
+    /// up written there.
+    pub fn set_generated(&mut self, path: &Path, content: String) {
+        self.generated.insert(normalize(path), content);
+    }
+
+    pub fn generated(&self) -> &BTreeMap<PathBuf, String> {
+        &self.generated
+    }
+
+    /// Path on disk holding `path` right now, unless a staged change says
+    /// there's nothing or gives the answer itself.
+    fn lookup(&self, path: &Path) -> Result<PathBuf, Option<&Entry>> {
+        let path = normalize(path);
+        for ancestor in path.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            let Some(entry) = self.staged.get(ancestor) else {
+                continue;
+            };
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            match entry {
+                _ if ancestor == path => {
+                    return match entry {
+                        Entry::Moved(from) => Ok(self.root.join(from)),
+                        entry => Err(Some(entry)),
+                    };
+                }
+                Entry::Moved(from) => return Ok(self.root.join(from).join(rest)),
+                Entry::File(_) | Entry::NewDir | Entry::Missing => return Err(None),
+            }
+        }
+        Ok(self.root.join(path))
+    }
+
+    pub fn kind(&self, path: &Path) -> Option<Kind> {
+        match self.lookup(path) {
+            Ok(disk_path) => match fs::symlink_metadata(disk_path) {
+                Ok(metadata) if metadata.is_dir() => Some(Kind::Dir),
+                Ok(_) => Some(Kind::File),
+                Err(_) => None,
+            },
+            Err(Some(Entry::File(_))) => Some(Kind::File),
+            Err(Some(Entry::NewDir)) => Some(Kind::Dir),
+            Err(_) => None,
+        }
+    }
+
+    pub fn exists(&self, path: &Path) -> bool {
+        self.kind(path).is_some()
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        match self.lookup(path) {
+            Ok(disk_path) => fs::read_to_string(disk_path),
+            Err(Some(Entry::File(content))) => Ok(content.clone()),
+            Err(Some(_)) => Err(io::Error::other(format!(
+                "{} is a directory",
+                path.display()
+            ))),
+            Err(None) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} doesn't exist", path.display()),
+            )),
+        }
+    }
+
+    /// Marks parents of `path` as directories, failing when one is a file.
+    fn stage_parents(&mut self, path: &Path) -> io::Result<()> {
+        let parents: Vec<PathBuf> = normalize(path)
+            .ancestors()
+            .skip(1)
+            .filter(|a| !a.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+        for parent in parents.into_iter().rev() {
+            match self.kind(&parent) {
+                Some(Kind::Dir) => (),
+                Some(Kind::File) => return Err(not_a_directory(&parent)),
+                None => _ = self.staged.insert(parent, Entry::NewDir),
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets staged children of `path`, they went away with it.
+    fn unstage_children(&mut self, path: &Path) -> Vec<(PathBuf, Entry)> {
+        let children: Vec<PathBuf> = self
+            .staged
+            .keys()
+            .filter(|key| key.starts_with(path) && *key != path)
+            .cloned()
+            .collect();
+        children
+            .into_iter()
+            .filter_map(|key| self.staged.remove(&key).map(|entry| (key, entry)))
+            .collect()
+    }
+
+    pub fn write(&mut self, path: &Path, content: String, mode: Option<u32>) -> io::Result<()> {
+        if self.kind(path) == Some(Kind::Dir) {
+            return Err(io::Error::other(format!(
+                "Can't write {}: it's a directory",
+                path.display()
+            )));
+        }
+        self.stage_parents(path)?;
+        self.staged
+            .insert(normalize(path), Entry::File(content.clone()));
+        self.changes.push(Change::Write {
+            path: path.to_path_buf(),
+            content,
+            mode,
+        });
+        Ok(())
+    }
+
+    pub fn create_dir(&mut self, path: &Path, mode: Option<u32>) -> io::Result<()> {
+        match self.kind(path) {
+            Some(Kind::File) => return Err(not_a_directory(path)),
+            Some(Kind::Dir) => (),
+            None => {
+                self.stage_parents(path)?;
+                self.staged.insert(normalize(path), Entry::NewDir);
+            }
+        }
+        self.changes.push(Change::CreateDir {
+            path: path.to_path_buf(),
+            mode,
+        });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let key = normalize(path);
+        self.unstage_children(&key);
+        self.staged.insert(key, Entry::Missing);
+        self.changes.push(Change::Remove {
+            path: path.to_path_buf(),
+        });
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        self.stage_parents(to)?;
+        let (from_key, to_key) = (normalize(from), normalize(to));
+        let entry = match self.lookup(from) {
+            Ok(disk_path) => {
+                let relative = disk_path.strip_prefix(&self.root).unwrap_or(&disk_path);
+                Entry::Moved(relative.to_path_buf())
+            }
+            Err(Some(entry)) => entry.clone(),
+            Err(None) => {
+                return Err(io::Error::other(format!(
+                    "Can't move {}: not found",
+                    from.display()
+                )));
+            }
+        };
+        for (key, child) in self.unstage_children(&from_key) {
+            let rest = key.strip_prefix(&from_key).unwrap_or(&key);
+            self.staged.insert(to_key.join(rest), child);
+        }
+        self.staged.insert(from_key, Entry::Missing);
+        self.staged.insert(to_key, entry);
+        self.changes.push(Change::Move {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Writes staged files next to the target, then moves them into place
+    /// one rename at a time. On error or Ctrl-C everything done so far is
+    /// undone, so the target is left as it was.
+    pub fn commit(self) -> io::Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        install_interrupt_handler();
+        let staging = self
+            .root
+            .join(format!(".tmplr-staging-{}", std::process::id()));
+        COMMITTING.store(true, Ordering::SeqCst);
+        // Missing output directory is created first and removed last, as
+        // staging directory lives inside of it
+        let mut created_root = Vec::new();
+        let mut journal = Vec::new();
+        let result = create_parents(&staging, &mut created_root)
+            .and_then(|_| fs::create_dir(&staging))
+            .and_then(|_| self.stage(&staging))
+            .and_then(|staged| self.apply(&staging, staged, &mut journal));
+        if result.is_err() {
+            rollback(journal);
+        }
+        _ = fs::remove_dir_all(&staging);
+        if result.is_err() {
+            rollback(created_root);
+        }
+        COMMITTING.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Writes contents of all files into the staging directory.
+    fn stage(&self, staging: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut staged = Vec::new();
+        for (index, change) in self.changes.iter().enumerate() {
+            let Change::Write { content, mode, .. } = change else {
+                continue;
+            };
+            let path = staging.join(format!("{}", index));
+            fs::write(&path, content)?;
+            if let Some(mode) = mode {
+                set_mode(&path, *mode)?;
+            }
+            check_interrupted()?;
+            staged.push(path);
+        }
+        Ok(staged)
+    }
+
+    fn apply(
+        &self,
+        staging: &Path,
+        staged: Vec<PathBuf>,
+        journal: &mut Vec<Undo>,
+    ) -> io::Result<()> {
+        let mut staged = staged.into_iter();
+        for (index, change) in self.changes.iter().enumerate() {
+            let backup = staging.join(format!("{}.orig", index));
+            match change {
+                Change::Write { path, mode, .. } => {
+                    let target = self.root.join(path);
+                    let source = staged
+                        .next()
+                        .ok_or_else(|| io::Error::other("Nothing staged"))?;
+                    create_parents(&target, journal)?;
+                    if let Ok(metadata) = fs::symlink_metadata(&target) {
+                        if mode.is_none() && metadata.is_file() {
+                            fs::set_permissions(&source, metadata.permissions())?;
+                        }
+                        fs::rename(&target, &backup)?;
+                        journal.push(Undo::Rename(backup, target.clone()));
+                    }
+                    fs::rename(&source, &target)?;
+                    journal.push(Undo::Remove(target));
+                }
+                Change::CreateDir { path, mode } => {
+                    let target = self.root.join(path);
+                    create_parents(&target, journal)?;
+                    match fs::symlink_metadata(&target) {
+                        Ok(metadata) if metadata.is_dir() => {
+                            journal.push(Undo::Permissions(target.clone(), metadata.permissions()))
+                        }
+                        Ok(_) => return Err(not_a_directory(path)),
+                        Err(_) => {
+                            fs::create_dir(&target)?;
+                            journal.push(Undo::RemoveDir(target.clone()));
+                        }
+                    }
+                    if let Some(mode) = mode {
+                        set_mode(&target, *mode)?;
+                    }
+                }
+                Change::Remove { path } => {
+                    let target = self.root.join(path);
+                    fs::rename(&target, &backup)?;
+                    journal.push(Undo::Rename(backup, target));
+                }
+                Change::Move { from, to } => {
+                    let (from, to) = (self.root.join(from), self.root.join(to));
+                    create_parents(&to, journal)?;
+                    fs::rename(&from, &to)?;
+                    journal.push(Undo::Rename(to, from));
+                }
+            }
+            check_interrupted()?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates missing parents of `path`, remembering them for rollback.
+fn create_parents(path: &Path, journal: &mut Vec<Undo>) -> io::Result<()> {
+    let Some(parent) = path.parent() else {
+        return Ok(());
+    };
+    let missing: Vec<&Path> = parent
+        .ancestors()
+        .take_while(|a| !a.as_os_str().is_empty() && fs::symlink_metadata(a).is_err())
+        .collect();
+    for dir in missing.into_iter().rev() {
+        fs::create_dir(dir)?;
+        journal.push(Undo::RemoveDir(dir.to_path_buf()));
+    }
+    Ok(())
+}
+
+fn rollback(journal: Vec<Undo>) {
+    for undo in journal.into_iter().rev() {
+        let (result, path) = match &undo {
+            Undo::Remove(path) => (fs::remove_file(path), path),
+            Undo::RemoveDir(path) => (fs::remove_dir(path), path),
+            Undo::Rename(from, to) => (fs::rename(from, to), to),
+            Undo::Permissions(path, permissions) => {
+                (fs::set_permissions(path, permissions.clone()), path)
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("WARN: can't roll back {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+    #[cfg(not(unix))]
+    {
+        _ = (path, mode);
+        Ok(())
+    }
+}
+
+/// Ctrl-C while committing is turned into an error, so changes are rolled
+/// back. Otherwise nothing has been written yet and it just quits.
+fn install_interrupt_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        _ = ctrlc::set_handler(|| match COMMITTING.load(Ordering::SeqCst) {
+            true => INTERRUPTED.store(true, Ordering::SeqCst),
+            false => std::process::exit(130),
+        });
+    });
+}
+
+fn check_interrupted() -> io::Result<()> {
+    match INTERRUPTED.load(Ordering::SeqCst) {
+        true => Err(io::Error::new(io::ErrorKind::Interrupted, "Interrupted")),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("tmplr-{}-{}", name, std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn reads_see_staged_changes() -> io::Result<()> {
+        let root = temp_root("staged-reads");
+        fs::create_dir_all(root.join("src"))?;
+        fs::write(root.join("src/lib.rs"), "lib\n")?;
+
+        let mut transaction = Transaction::new(&root);
+        transaction.write(Path::new("./README.md"), "readme\n".into(), None)?;
+        assert_eq!(
+            transaction.read_to_string(Path::new("README.md"))?,
+            "readme\n"
+        );
+        transaction.rename(Path::new("src"), Path::new("core"))?;
+        assert_eq!(
+            transaction.read_to_string(Path::new("core/lib.rs"))?,
+            "lib\n"
+        );
+        assert!(!transaction.exists(Path::new("src/lib.rs")));
+        transaction.remove(Path::new("core"));
+        transaction.write(Path::new("core/mod.rs"), "mod\n".into(), None)?;
+        assert!(!transaction.exists(Path::new("core/lib.rs")));
+        assert!(
+            transaction
+                .write(Path::new("README.md/x"), "".into(), None)
+                .is_err()
+        );
+
+        assert!(!root.join("README.md").exists());
+        transaction.commit()?;
+        assert_eq!(fs::read_to_string(root.join("core/mod.rs"))?, "mod\n");
+        assert!(!root.join("core/lib.rs").exists());
+        assert!(!root.join("src").exists());
+        fs::remove_dir_all(root)
+    }
+
+    #[test]
+    fn failed_commit_rolls_back() -> io::Result<()> {
+        let root = temp_root("rollback");
+        fs::write(root.join("keep.txt"), "old\n")?;
+
+        let mut transaction = Transaction::new(&root);
+        transaction.write(Path::new("keep.txt"), "new\n".into(), None)?;
+        transaction.write(Path::new("a/b/new.txt"), "new\n".into(), None)?;
+        transaction.remove(Path::new("missing.txt"));
+        assert!(transaction.commit().is_err());
+
+        assert_eq!(fs::read_to_string(root.join("keep.txt"))?, "old\n");
+        let left: Vec<_> = fs::read_dir(&root)?.collect();
+        assert_eq!(left.len(), 1);
+
+        let mut transaction = Transaction::new(&root.join("out/app"));
+        transaction.write(Path::new("new.txt"), "new\n".into(), None)?;
+        transaction.remove(Path::new("missing.txt"));
+        assert!(transaction.commit().is_err());
+        assert!(!root.join("out").exists());
+        fs::remove_dir_all(root)
+    }
+}
@@ -0,0 +1,201 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+use crate::{
+    ConvertArgs,
+    error_handling::UnwrapReport,
+    template::{Delimiters, Node, get_template_string_from_path, parse_template, write_template},
+};
+
+pub const EXTENSION: &str = "txtar";
+
+/// Formats `tmplr convert` reads and writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Tmplr,
+    Txtar,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tmplr" => Ok(Format::Tmplr),
+            "txtar" => Ok(Format::Txtar),
+            _ => Err(format!(
+                "Invalid format value: {} (expected tmplr or txtar)",
+                s
+            )),
+        }
+    }
+}
+
+/// Go's txtar archive: free-form comment followed by `-- path --` files.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Archive {
+    pub comment: String,
+    pub files: Vec<(String, String)>,
+}
+
+fn file_marker(line: &str) -> Option<&str> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let name = line.strip_prefix("-- ")?.strip_suffix(" --")?.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+pub fn parse(text: &str) -> Archive {
+    let mut archive = Archive::default();
+    let mut current = &mut archive.comment;
+    for line in text.split_inclusive('\n') {
+        if let Some(name) = file_marker(line) {
+            archive.files.push((name.into(), String::new()));
+            current = &mut archive.files.last_mut().unwrap().1;
+            continue;
+        }
+        current.push_str(line);
+    }
+    archive
+}
+
+/// Like Go's `txtar.Format`, comment and file contents get final newline.
+pub fn format(archive: &Archive) -> String {
+    fn push_text(result: &mut String, text: &str) {
+        result.push_str(text);
+        if !text.is_empty() && !text.ends_with('\n') {
+            result.push('\n');
+        }
+    }
+    let mut result = String::new();
+    push_text(&mut result, &archive.comment);
+    for (name, content) in &archive.files {
+        result.push_str(&format!("-- {} --\n", name));
+        push_text(&mut result, content);
+    }
+    result
+}
+
+/// Archive comment becomes template preamble, files become `FILE` sections.
+pub fn to_template(text: &str) -> String {
+    let archive = parse(text);
+    let mut preamble = archive.comment;
+    if !preamble.is_empty() && !preamble.ends_with('\n') {
+        preamble.push('\n');
+    }
+    write_template(&preamble, &[], &archive.files)
+}
+
+/// Only `FILE` sections fit in txtar, others are skipped and attributes of
+/// `FILE` sections dropped with a warning.
+pub fn from_template(text: &str) -> io::Result<String> {
+    let sections = Delimiters::sections();
+    let comment = match text.find(&sections.open) {
+        Some(start) => &text[..start],
+        None => text,
+    };
+    let mut archive = Archive {
+        comment: comment.into(),
+        files: Vec::new(),
+    };
+    for node in parse_template(text)? {
+        match node {
+            Node::File {
+                path,
+                content,
+                attrs,
+            } => {
+                if !attrs.is_empty() {
+                    eprintln!(
+                        "WARN: txtar can't hold attributes, dropping `{}` of FILE {}",
+                        attrs, path
+                    );
+                }
+                archive.files.push((path, content));
+            }
+            other => eprintln!(
+                "WARN: txtar only holds FILE sections, skipping {}",
+                describe(&other)
+            ),
+        }
+    }
+    Ok(format(&archive))
+}
+
+fn describe(node: &Node) -> String {
+    match node {
+        Node::Dir { path, .. } => format!("DIR {}", path.display()),
+        Node::Ext { path, .. } => format!("EXT {}", path),
+        Node::Prepend { path, .. } => format!("PREPEND {}", path),
+        Node::Insert { path, .. } => format!("INSERT {}", path),
+        Node::Patch { path, .. } => format!("PATCH {}", path),
+        Node::Merge { path, .. } => format!("MERGE {}", path),
+        Node::Block { path, .. } => format!("BLOCK {}", path),
+        Node::Remove { path, .. } => format!("REMOVE {}", path),
+        Node::Move { from, to, .. } => format!("MOVE {} -> {}", from, to),
+        Node::Run { .. } => "RUN".into(),
+        Node::Require { args, .. } => format!("REQUIRE {}", args.join(" ")),
+        Node::Var { name, .. } => format!("VAR {}", name),
+        Node::File { path, .. } => format!("FILE {}", path),
+    }
+}
+
+pub fn is_txtar(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// Missing `--from` comes from the input extension, missing `--to` is the
+/// other format.
+pub fn run_convert(args: &ConvertArgs) {
+    let path = &args.input;
+    let from = args.from.unwrap_or(match is_txtar(path) {
+        true => Format::Txtar,
+        false => Format::Tmplr,
+    });
+    let to = args.to.unwrap_or(match from {
+        Format::Txtar => Format::Tmplr,
+        Format::Tmplr => Format::Txtar,
+    });
+    let output = match (from, to) {
+        (Format::Txtar, Format::Tmplr) => fs::read_to_string(path).map(|text| to_template(&text)),
+        (Format::Tmplr, Format::Txtar) => {
+            get_template_string_from_path(path).and_then(|text| from_template(&text))
+        }
+        _ => Err(io::Error::other(
+            "Nothing to convert, --from and --to are the same format",
+        )),
+    };
+    print!("{}", output.unwrap_or_report(1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHIVE: &str =
+        "Fixture for {{ name }}.\n-- go.mod --\nmodule {{ name }}\n-- main.go --\npackage main\n";
+
+    #[test]
+    fn parses_and_formats_archive() {
+        let archive = parse(ARCHIVE);
+        assert_eq!(archive.comment, "Fixture for {{ name }}.\n");
+        assert_eq!(
+            archive.files,
+            vec![
+                ("go.mod".to_string(), "module {{ name }}\n".to_string()),
+                ("main.go".to_string(), "package main\n".to_string()),
+            ]
+        );
+        assert_eq!(format(&archive), ARCHIVE);
+    }
+
+    #[test]
+    fn converts_both_ways() -> io::Result<()> {
+        let template = to_template(ARCHIVE);
+        assert_eq!(
+            template,
+            "Fixture for {{ name }}.\n{### FILE go.mod ###}\nmodule {{ name }}\n{### FILE main.go ###}\npackage main\n"
+        );
+        assert_eq!(from_template(&template)?, ARCHIVE);
+        Ok(())
+    }
+}
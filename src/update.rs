@@ -0,0 +1,113 @@
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    MakeArgs, UpdateArgs, conflict,
+    error_handling::{UnwrapReport, quit_with_error},
+    manifest::{self, Manifest, Record},
+    render_template, template,
+};
+
+/// Renders templates recorded in the manifest again with recorded
+/// variables, in the order they were made. `FILE` sections are merged with
+/// local changes against what the template generated last time. Edits of
+/// other files and hooks were applied by `make` and aren't repeated.
+pub fn run_update(args: &UpdateArgs) {
+    let root = match &args.output {
+        Some(output) => output.clone(),
+        None => current_dir().unwrap_or_report(1),
+    };
+    let text = fs::read_to_string(root.join(manifest::PATH))
+        .map_err(|_| {
+            format!(
+                "{} not found in {}, update works on output of `tmplr make`",
+                manifest::PATH,
+                root.display()
+            )
+        })
+        .unwrap_or_report(1);
+    let manifest = Manifest::parse(&text).unwrap_or_report(1);
+    if manifest.templates.is_empty() {
+        quit_with_error(1, &format!("{} records no templates", manifest::PATH));
+    }
+    match &args.template_path {
+        Some(template_path) => {
+            let record = given_record(&manifest, template_path);
+            update_record(args, &root, record, template_path.clone());
+        }
+        None => {
+            for record in &manifest.templates {
+                update_record(args, &root, record, recorded_template(record));
+            }
+        }
+    }
+}
+
+fn update_record(args: &UpdateArgs, root: &Path, record: &Record, template_path: PathBuf) {
+    let mut variables = args.variables.clone();
+    for (name, value) in &record.variables {
+        if variables.contains_key(name) {
+            continue;
+        }
+        if value == manifest::MASK {
+            quit_with_error(
+                1,
+                &format!(
+                    "Variable `{}` is secret and wasn't recorded, pass {}=VALUE",
+                    name, name
+                ),
+            );
+        }
+        variables.insert(name.clone(), value.clone());
+    }
+
+    render_template::make(&MakeArgs {
+        template_path,
+        variables,
+        dry_run: false,
+        diff: args.diff,
+        eol: None,
+        yes: args.yes,
+        no_hooks: true,
+        on_conflict: conflict::Policy::Merge,
+        output: Some(root.to_path_buf()),
+        into_name: false,
+    });
+}
+
+/// Record a template given in place of the recorded one updates: the only
+/// one, or the one recorded under the same name.
+fn given_record<'a>(manifest: &'a Manifest, template_path: &Path) -> &'a Record {
+    if let [record] = manifest.templates.as_slice() {
+        return record;
+    }
+    let path = template::resolve_template_path(template_path).unwrap_or_report(1);
+    let name = manifest::template_name(&path);
+    let Some(record) = manifest.find(&name) else {
+        let recorded: Vec<&str> = manifest.templates.iter().map(|r| r.name.as_str()).collect();
+        quit_with_error(
+            1,
+            &format!(
+                "`{}` isn't recorded in {}, it records {}",
+                name,
+                manifest::PATH,
+                recorded.join(", ")
+            ),
+        );
+        unreachable!();
+    };
+    record
+}
+
+/// Recorded source of the template while it's still there, library name
+/// otherwise.
+fn recorded_template(record: &Record) -> PathBuf {
+    let source = PathBuf::from(&record.source);
+    match source.exists() {
+        true => source,
+        false => PathBuf::from(&record.name),
+    }
+}
@@ -1,11 +1,35 @@
 mod cli_tests {
     mod basic_tests;
+    mod comment_tests;
+    mod conflict_tests;
     mod create_template_tests;
+    mod delimiters_tests;
+    mod diff_tests;
+    mod import_tests;
     mod list_tests;
+    mod literate_tests;
+    mod output_tests;
     mod preview_tests;
+    mod require_tests;
+    mod sandbox_tests;
     mod template_dir_tests;
+    mod transaction_tests;
+    mod txtar_tests;
+    mod update_tests;
+    mod unroll_block_tests;
     mod unroll_extension_tests;
+    mod unroll_insert_tests;
+    mod unroll_merge_tests;
+    mod unroll_move_tests;
+    mod unroll_patch_tests;
     mod unroll_tests;
+    mod var_tests;
     mod magic_variable_tests;
+    mod manifest_tests;
     mod echo_tests;
+    mod eol_tests;
+    mod whitespace_tests;
+    mod header_attributes_tests;
+    mod keep_regions_tests;
+    mod hooks_tests;
 }
@@ -0,0 +1,46 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn comments_dont_reach_output() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE main.rs ###}
+{### # entry point, keep it short ###}
+fn main() {
+    {#- TODO: arguments -#}
+    println!("{{ name }}"); {#- greeting -#}
+}
+{#-
+  Helpers follow
+-#}
+{### # -------- ###}
+{### FILE lib.rs ###}
+pub fn {{ name }}() {}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("hello")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("main.rs")
+        .assert(predicate::eq("fn main() {\n    println!(\"hello\"); \n}\n"));
+    unroll_dir
+        .child("lib.rs")
+        .assert(predicate::eq("pub fn hello() {}\n"));
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+const TEMPLATE: &str = r#"{### FILE README.md ###}
+# {{ name }}
+{### FILE src/main.rs ###}
+fn main() {}
+"#;
+
+fn make(on_conflict: &str) -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let _ = unroll_dir.child("README.md").write_str("# Local notes\n");
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(TEMPLATE);
+
+    let assert = Command::new(COMMAND)
+        .arg("make")
+        .arg(format!("--on-conflict={}", on_conflict))
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert();
+    match on_conflict {
+        "skip" => {
+            assert
+                .success()
+                .stdout(predicate::str::contains("Skipping: README.md"));
+            unroll_dir.child("README.md").assert("# Local notes\n");
+            unroll_dir.child("src/main.rs").assert("fn main() {}\n");
+        }
+        "error" => {
+            assert.failure().stderr(predicate::str::contains(
+                "README.md already exists and differs from template",
+            ));
+            unroll_dir.child("README.md").assert("# Local notes\n");
+            unroll_dir
+                .child("src/main.rs")
+                .assert(predicate::path::missing());
+        }
+        "rename" => {
+            assert
+                .success()
+                .stdout(predicate::str::contains("Writing: README.md.new"));
+            unroll_dir.child("README.md").assert("# Local notes\n");
+            unroll_dir.child("README.md.new").assert("# app\n");
+        }
+        "prompt" => {
+            assert
+                .failure()
+                .stdout(predicate::str::contains("-# Local notes\n+# app\n"))
+                .stderr(predicate::str::contains("Can't ask about README.md"));
+            unroll_dir.child("README.md").assert("# Local notes\n");
+        }
+        _ => {
+            assert.success();
+            unroll_dir.child("README.md").assert("# app\n");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn overwrites_by_default() -> TestResult {
+    make("overwrite")
+}
+
+#[test]
+fn skips_existing_files() -> TestResult {
+    make("skip")
+}
+
+#[test]
+fn fails_on_existing_files() -> TestResult {
+    make("error")
+}
+
+#[test]
+fn writes_next_to_existing_files() -> TestResult {
+    make("rename")
+}
+
+#[test]
+fn renamed_files_are_not_overwritten() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let _ = unroll_dir.child("README.md").write_str("# Local notes\n");
+    let _ = unroll_dir.child("README.md.new").write_str("# Draft\n");
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(TEMPLATE);
+
+    for _ in 0..2 {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg("--on-conflict=rename")
+            .arg(template_path.path())
+            .arg("app")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+    unroll_dir.child("README.md.new").assert("# Draft\n");
+    unroll_dir.child("README.md.new.1").assert("# app\n");
+    unroll_dir
+        .child("README.md.new.2")
+        .assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn prompt_shows_diff() -> TestResult {
+    make("prompt")
+}
+
+#[test]
+fn once_writes_only_missing_files() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let _ = unroll_dir.child(".env").write_str("SECRET=local\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE .env once=true ###}
+SECRET=changeme
+{### FILE .env.example once ###}
+SECRET=changeme
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--on-conflict=error")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Skipping: .env (already exists)"))
+        .stdout(predicate::str::contains("Writing: .env.example"));
+
+    unroll_dir.child(".env").assert("SECRET=local\n");
+    unroll_dir.child(".env.example").assert("SECRET=changeme\n");
+    Ok(())
+}
+
+#[test]
+fn invalid_policy_is_reported() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(TEMPLATE);
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--on-conflict=merge")
+        .arg(template_path.path())
+        .arg("app")
+        .assert()
+        .stdout(predicate::str::contains("Invalid on-conflict value: merge"));
+    Ok(())
+}
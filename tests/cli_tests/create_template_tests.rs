@@ -175,3 +175,82 @@ fn only_matching_and_working_dir() -> TestResult {
         .assert(predicate::str::contains("{### FILE file3.txt ###}").not());
     Ok(())
 }
+#[test]
+fn quotes_paths_with_spaces() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+
+    _ = template_dir
+        .child("ROOT")
+        .child("my notes.txt")
+        .write_str("Content: TEST");
+
+    let mut cmd = Command::new(COMMAND);
+
+    cmd.arg("create")
+        .arg("TEST")
+        .args(["-C", "ROOT"])
+        .current_dir(&template_dir)
+        .assert()
+        .success();
+
+    template_dir
+        .child("TEST.tmplr")
+        .assert(predicate::str::contains(r#"{### FILE "my notes.txt" ###}"#));
+    Ok(())
+}
+
+#[test]
+fn content_with_section_markers_round_trips() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let content = "Sections look like:\n{### FILE a.txt ###}\nbody\n";
+
+    _ = template_dir.child("notes.md").write_str(content);
+
+    Command::new(COMMAND)
+        .arg("create")
+        .arg("TEST")
+        .current_dir(&template_dir)
+        .assert()
+        .success();
+    template_dir
+        .child("TEST.tmplr")
+        .assert(predicate::str::starts_with("{### DELIMITERS"));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_dir.child("TEST.tmplr").path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir.child("notes.md").assert(content);
+    unroll_dir.child("a.txt").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn jinja_comments_survive_create() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let content = "{#- layout for all pages -#}\n{% extends \"base.html\" %}\n";
+
+    _ = template_dir.child("page.html.j2").write_str(content);
+
+    Command::new(COMMAND)
+        .arg("create")
+        .arg("TEST")
+        .current_dir(&template_dir)
+        .assert()
+        .success();
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_dir.child("TEST.tmplr").path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir.child("page.html.j2").assert(content);
+    Ok(())
+}
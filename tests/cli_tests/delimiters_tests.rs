@@ -0,0 +1,130 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn preamble_delimiters() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+Helm chart
+{### DELIMITERS <% %> ###}
+{### FILE <% name %>/values.yaml ###}
+name: <% name %>
+image: {{ .Values.image }}
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("chart")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("chart/values.yaml")
+        .assert(predicate::str::contains("name: chart"))
+        .assert(predicate::str::contains("image: {{ .Values.image }}"));
+    Ok(())
+}
+
+#[test]
+fn section_delimiters() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### DELIMITERS sections="<@ @>" ###}
+<@ FILE doc.md @>
+Example: {### FILE x ###}
+Name: {{ name }}
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("doc.md")
+        .assert(predicate::str::contains("Example: {### FILE x ###}"))
+        .assert(predicate::str::contains("Name: TEST"));
+    unroll_dir
+        .child("x")
+        .assert(predicate::path::exists().not());
+    Ok(())
+}
+
+#[test]
+fn per_file_delimiters() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE {{ name }}.mustache delims="[[ ]]" ###}
+Hello {{ name }} from [[ name ]]
+{### FILE plain.txt ###}
+Hello {{ name }} from [[ name ]]
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("{{ name }}.mustache")
+        .assert(predicate::str::contains("Hello {{ name }} from TEST"));
+    unroll_dir
+        .child("plain.txt")
+        .assert(predicate::str::contains("Hello TEST from [[ name ]]"));
+    Ok(())
+}
+
+#[test]
+fn invalid_delimiters_fail() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE file.txt delims="[[" ###}
+content
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .stderr(predicate::str::contains("Delimiters should be given as"));
+
+    unroll_dir
+        .child("file.txt")
+        .assert(predicate::path::exists().not());
+    Ok(())
+}
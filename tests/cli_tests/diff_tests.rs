@@ -0,0 +1,81 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn shows_changes_without_writing() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("notes.txt").write_str("line1\nline2\n");
+    let _ = unroll_dir.child("README.md").write_str("# app\n");
+    let _ = unroll_dir.child("old.txt").write_str("bye\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE src/{{ name }}.rs ###}
+pub fn run() {}
+{### EXT notes.txt ###}
+line3
+{### FILE README.md ###}
+# {{ name }}
+{### DIR assets ###}
+{### REMOVE old.txt ###}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--diff")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "new: src/app.rs\n--- /dev/null\n+++ src/app.rs\n@@ -0,0 +1,1 @@\n+pub fn run() {}\n",
+        ))
+        .stdout(predicate::str::contains(
+            "modified: notes.txt\n--- notes.txt\n+++ notes.txt\n@@ -1,2 +1,3 @@\n line1\n line2\n+line3\n",
+        ))
+        .stdout(predicate::str::contains("unchanged: README.md\n"))
+        .stdout(predicate::str::contains("new: assets/\n"))
+        .stdout(predicate::str::contains(
+            "removed: old.txt\n--- old.txt\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-bye\n",
+        ))
+        .stdout(predicate::str::contains("Writing").not());
+
+    unroll_dir.child("src").assert(predicate::path::missing());
+    unroll_dir.child("assets").assert(predicate::path::missing());
+    unroll_dir.child("notes.txt").assert("line1\nline2\n");
+    unroll_dir.child("old.txt").assert("bye\n");
+    Ok(())
+}
+
+#[test]
+fn follows_conflict_policy() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("README.md").write_str("# Local\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE README.md ###}\n# {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--diff")
+        .arg("--on-conflict=rename")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("new: README.md.new\n"))
+        .stdout(predicate::str::contains("modified: README.md").not());
+    Ok(())
+}
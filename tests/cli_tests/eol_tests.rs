@@ -0,0 +1,133 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn eol_attribute() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        "{### FILE run.bat eol=crlf ###}\n@echo off\necho {{ name }}\n{### FILE run.sh eol=lf ###}\r\n#!/bin/sh\r\necho {{ name }}\r\n",
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("run.bat")
+        .assert(predicate::eq("@echo off\r\necho TEST\r\n"));
+    unroll_dir
+        .child("run.sh")
+        .assert(predicate::eq("#!/bin/sh\necho TEST\n"));
+    Ok(())
+}
+
+#[test]
+fn global_eol_flag() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        "{### FILE run.sh ###}\r\n#!/bin/sh\r\necho hi\r\n{### FILE run.bat eol=crlf ###}\r\necho hi\r\n",
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .args(["--eol", "lf"])
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("run.sh")
+        .assert(predicate::eq("#!/bin/sh\necho hi\n"));
+    unroll_dir
+        .child("run.bat")
+        .assert(predicate::eq("echo hi\r\n"));
+    Ok(())
+}
+
+#[test]
+fn bom_attribute() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE script.ps1 bom=true ###}\nWrite-Host hi\n");
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("script.ps1")
+        .assert(predicate::eq("\u{feff}Write-Host hi\n"));
+    Ok(())
+}
+
+#[test]
+fn create_records_eol_and_bom() -> TestResult {
+    let source_dir = assert_fs::TempDir::new()?;
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let files = [
+        ("build.bat", "@echo off\r\necho TEST\r\n"),
+        ("profile.ps1", "\u{feff}Write-Host TEST\r\n"),
+        ("mixed.txt", "a\r\nb\n"),
+        ("plain.sh", "echo TEST\n"),
+    ];
+    for (path, content) in files {
+        _ = source_dir.child(path).write_str(content);
+    }
+
+    Command::new(COMMAND)
+        .arg("create")
+        .arg("TEST")
+        .arg("-C")
+        .arg(source_dir.path())
+        .current_dir(&template_dir)
+        .assert()
+        .success();
+
+    template_dir
+        .child("TEST.tmplr")
+        .assert(predicate::str::contains(
+            "{### FILE build.bat eol=crlf ###}",
+        ))
+        .assert(predicate::str::contains(
+            "{### FILE profile.ps1 bom=true eol=crlf ###}",
+        ))
+        .assert(predicate::str::contains("{### FILE plain.sh ###}"));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_dir.child("TEST.tmplr").path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    for (path, content) in files {
+        unroll_dir.child(path).assert(predicate::eq(content));
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn quoted_path_with_spaces() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE "dir with space/{{ name }} notes.txt" ###}
+Hello {{name}}
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Writing: dir with space/TEST notes.txt",
+        ));
+
+    unroll_dir
+        .child("dir with space/TEST notes.txt")
+        .assert(predicate::str::contains("Hello TEST"));
+    Ok(())
+}
+
+#[test]
+fn if_attribute_skips_sections() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE ci.yml if=ci ###}
+ci
+{### FILE local.txt if=!ci ###}
+local
+{### DIR docs if=docs ###}
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .arg("ci=true")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir.child("ci.yml").assert(predicate::path::exists());
+    unroll_dir
+        .child("local.txt")
+        .assert(predicate::path::exists().not());
+    unroll_dir
+        .child("docs")
+        .assert(predicate::path::exists().not());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn mode_attribute_sets_permissions() -> TestResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE run.sh mode={{ perm }} ###}
+#!/bin/sh
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .arg("perm=750")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    let mode = std::fs::metadata(unroll_dir.child("run.sh").path())?
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o7777, 0o750);
+    Ok(())
+}
+
+#[test]
+fn unknown_attribute_warns() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE file.txt colour=red ###}
+content
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "WARN: Unknown attribute `colour` in FILE section",
+        ));
+
+    unroll_dir
+        .child("file.txt")
+        .assert(predicate::path::exists());
+    Ok(())
+}
+
+#[test]
+fn invalid_mode_fails() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"
+{### FILE file.txt mode=rwx ###}
+content
+"#,
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid mode attribute: rwx"));
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[cfg(unix)]
+#[test]
+fn library_template_runs_hooks_after_files() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    _ = template_dir.child("tmplr").child("hook.tmplr").write_str(
+        r#"{### FILE {{ name }}.txt ###}
+hello
+{### RUN ###}
+cat {{ name }}.txt > copy.txt
+echo "$TMPLR_NAME $TMPLR_CRATE_TYPE" >> copy.txt
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("hook.tmplr")
+        .arg("app")
+        .arg("crate-type=bin")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Running: cat app.txt > copy.txt"));
+
+    unroll_dir
+        .child("copy.txt")
+        .assert(predicate::eq("hello\napp bin\n"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn untrusted_template_needs_confirmation() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE a.txt ###}\na\n{### RUN ###}\ntouch ran\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use --yes"));
+    // Asked before files are written
+    unroll_dir.child("a.txt").assert(predicate::path::missing());
+    unroll_dir.child("ran").assert(predicate::path::missing());
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir.child("ran").assert(predicate::path::exists());
+    Ok(())
+}
+
+#[test]
+fn no_hooks_skips_run_sections() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE a.txt ###}\na\n{### RUN ###}\ntouch ran\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--no-hooks")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Skipping hooks"));
+    unroll_dir.child("a.txt").assert(predicate::path::exists());
+    unroll_dir.child("ran").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_hook_reports_output() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### RUN ###}\necho broken build >&2\nexit 3\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-y")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Hook failed"))
+        .stderr(predicate::str::contains("broken build"));
+    Ok(())
+}
+
+#[test]
+fn dry_run_lists_hooks() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### RUN ###}\ngit init {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-n")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{### RUN ###}\ngit init app\n"));
+    unroll_dir.child("app").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn variables_are_quoted_in_hooks() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### RUN ###}\ntouch {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg(template_path.path())
+        .arg("my app; touch injected")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Running: touch 'my app; touch injected'",
+        ));
+    unroll_dir
+        .child("my app; touch injected")
+        .assert(predicate::path::exists());
+    unroll_dir
+        .child("injected")
+        .assert(predicate::path::missing());
+    Ok(())
+}
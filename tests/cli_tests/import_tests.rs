@@ -0,0 +1,156 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+fn cookiecutter_template(dir: &assert_fs::TempDir) -> assert_fs::fixture::ChildPath {
+    let source = dir.child("python-lib");
+    _ = source.child("cookiecutter.json").write_str(
+        r#"{
+  "project_slug": "mylib",
+  "license": ["MIT", "Apache-2.0"],
+  "__prompts__": {"project_slug": "Package name"}
+}"#,
+    );
+    let root = source.child("{{cookiecutter.project_slug}}");
+    _ = root
+        .child("pyproject.toml")
+        .write_str("[project]\nname = \"{{ cookiecutter.project_slug }}\"\nlicense = \"{{cookiecutter.license}}\"\n");
+    _ = root
+        .child("src")
+        .child("{{cookiecutter.project_slug}}")
+        .child("__init__.py")
+        .write_str("{% if cookiecutter.license == 'MIT' %}# MIT{% endif %}\n");
+    source
+}
+
+#[test]
+fn imports_cookiecutter_as_template_file() -> TestResult {
+    let work_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let source = cookiecutter_template(&work_dir);
+    let output = work_dir.child("python-lib.tmplr");
+
+    Command::new(COMMAND)
+        .arg("import")
+        .arg("cookiecutter")
+        .arg(source.path())
+        .arg(output.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 2 files"))
+        .stderr(predicate::str::contains(
+            "__init__.py:1: unsupported Jinja `{% if cookiecutter.license == 'MIT' %}`",
+        ));
+
+    output.assert(predicate::str::contains(
+        "{### VAR project_slug default=mylib prompt=\"Package name\" ###}\n{### VAR license choices=MIT,Apache-2.0 ###}\n",
+    ));
+    output.assert(predicate::str::contains(
+        "{### FILE {{ project_slug }}/pyproject.toml ###}\n[project]\nname = \"{{ project_slug }}\"\n",
+    ));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(output.path())
+        .arg("app")
+        .arg("license=Apache-2.0")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("mylib/pyproject.toml")
+        .assert("[project]\nname = \"mylib\"\nlicense = \"Apache-2.0\"\n");
+    unroll_dir
+        .child("mylib/src/mylib/__init__.py")
+        .assert(predicate::path::exists());
+    Ok(())
+}
+
+#[test]
+fn imports_cookiecutter_as_directory_template() -> TestResult {
+    let work_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let source = cookiecutter_template(&work_dir);
+    let output = work_dir.child("python-lib-tmplr");
+
+    Command::new(COMMAND)
+        .arg("import")
+        .arg("cookiecutter")
+        .arg(source.path())
+        .arg(output.path())
+        .assert()
+        .success();
+
+    output.child("tmplr.toml").assert(predicate::str::contains(
+        "[vars.project_slug]\ndefault = \"mylib\"\nprompt = \"Package name\"\n\n[vars.license]\nchoices = [\"MIT\", \"Apache-2.0\"]\n",
+    ));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(output.path())
+        .arg("app")
+        .arg("project_slug=other")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("other/pyproject.toml")
+        .assert("[project]\nname = \"other\"\nlicense = \"MIT\"\n");
+    Ok(())
+}
+
+#[test]
+fn imports_into_current_dir_by_default() -> TestResult {
+    let work_dir = assert_fs::TempDir::new()?;
+    let source = cookiecutter_template(&work_dir);
+
+    Command::new(COMMAND)
+        .arg("import")
+        .arg("cookiecutter")
+        .arg(source.path())
+        .current_dir(&work_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("into python-lib.tmplr"));
+    work_dir
+        .child("python-lib.tmplr")
+        .assert(predicate::str::contains("{### VAR project_slug"));
+
+    Command::new(COMMAND)
+        .arg("import")
+        .arg("cookiecutter")
+        .arg(source.path())
+        .current_dir(&work_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("python-lib.tmplr already exists"));
+    Ok(())
+}
+
+#[test]
+fn reports_filters() -> TestResult {
+    let work_dir = assert_fs::TempDir::new()?;
+    let source = cookiecutter_template(&work_dir);
+    _ = source
+        .child("{{cookiecutter.project_slug}}")
+        .child("NAME")
+        .write_str("{{ cookiecutter.project_slug | upper }}\n");
+
+    Command::new(COMMAND)
+        .arg("import")
+        .arg("cookiecutter")
+        .arg(source.path())
+        .arg(work_dir.child("python-lib.tmplr").path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "NAME:1: unsupported Jinja `{{ cookiecutter.project_slug | upper }}`",
+        ));
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn regenerating_file_keeps_user_regions() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE client.rs ###}
+pub struct {{ name }}Client;
+
+impl {{ name }}Client {
+    // tmplr:keep begin methods
+    // tmplr:keep end
+}
+"#,
+    );
+
+    let make = |name: &str| {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg(name)
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    };
+    make("Api");
+
+    let generated = std::fs::read_to_string(unroll_dir.child("client.rs").path())?;
+    std::fs::write(
+        unroll_dir.child("client.rs").path(),
+        generated.replace(
+            "    // tmplr:keep end",
+            "    pub fn custom(&self) {}\n    // tmplr:keep end",
+        ),
+    )?;
+
+    make("Users");
+
+    unroll_dir.child("client.rs").assert(predicate::eq(
+        r#"pub struct UsersClient;
+
+impl UsersClient {
+    // tmplr:keep begin methods
+    pub fn custom(&self) {}
+    // tmplr:keep end
+}
+"#,
+    ));
+    Ok(())
+}
@@ -111,3 +111,62 @@ fn is_nicely_formatted_flat() -> TestResult {
 
     Ok(())
 }
+#[test]
+fn shows_metadata_summary() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let rust = template_dir.child("tmplr").child("rust");
+    _ = rust.child("cli.tmplr").write_str(
+        "{### META description=\"Rust CLI tool\" tags=rust,cli version=1.2 ###}\n{### FILE src/main.rs ###}\nfn main() {}\n",
+    );
+    _ = rust
+        .child("plain.tmplr")
+        .write_str("{### FILE a.txt ###}\na\n");
+
+    Command::new(COMMAND)
+        .arg("list")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  - cli.tmplr: Rust CLI tool [rust, cli]\n  - plain.tmplr\n",
+        ));
+
+    Command::new(COMMAND)
+        .arg("info")
+        .arg("rust/cli")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Description: Rust CLI tool\nTags: rust, cli\nVersion: 1.2\n",
+        ));
+    Ok(())
+}
+#[test]
+fn skips_git_and_plain_directories() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let library = template_dir.child("tmplr");
+    _ = library.child("ex1.tmplr").touch();
+    _ = library
+        .child(".git")
+        .child("HEAD")
+        .write_str("ref: refs/heads/main\n");
+    _ = library
+        .child(".git")
+        .child("hooks")
+        .child("old.tmplr")
+        .touch();
+    _ = library.child("docs").child("README.md").touch();
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("list")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ex1.tmplr"))
+        .stdout(predicate::str::contains(".git").not())
+        .stdout(predicate::str::contains("old.tmplr").not())
+        .stdout(predicate::str::contains("docs").not());
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn markdown_code_blocks_become_files() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("onboarding.md");
+    _ = template_path.write_str(
+        r#"# New service
+
+Every service starts with a manifest:
+
+```toml file=Cargo.toml
+[package]
+name = "{{ name }}"
+```
+
+Run it with:
+
+```sh
+cargo run
+```
+
+And the entry point:
+
+```rust file="src/main.rs"
+fn main() {}
+```
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("billing")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("Cargo.toml")
+        .assert("[package]\nname = \"billing\"\n");
+    unroll_dir.child("src/main.rs").assert("fn main() {}\n");
+    let written = std::fs::read_dir(unroll_dir.path())?
+        .filter(|entry| entry.as_ref().is_ok_and(|e| e.file_name() != ".tmplr"))
+        .count();
+    assert_eq!(written, 2);
+    Ok(())
+}
+
+#[test]
+fn dry_run_shows_markdown_files() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let template_path = template_dir.child("doc.markdown");
+    _ = template_path.write_str("Prose\n~~~ file=notes/{{ name }}.txt\nhi\n~~~\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--dry-run")
+        .arg(template_path.path())
+        .arg("x")
+        .current_dir(&template_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes/x.txt"));
+    Ok(())
+}
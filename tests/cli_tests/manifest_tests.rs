@@ -0,0 +1,168 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn records_generation() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("notes.txt").write_str("notes\n");
+
+    let template_path = template_dir.child("rust-lib.tmplr");
+    _ = template_path.write_str(
+        r#"{### VAR license secret=true ###}
+{### FILE src/{{ name }}.rs ###}
+pub fn {{ name }}() {}
+{### EXT notes.txt ###}
+{{ name }}
+{### DIR assets ###}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("core")
+        .arg("license=LIC-123")
+        .arg("api_token=abc")
+        .arg("edition=2024")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    let source = template_path.path().canonicalize()?;
+    unroll_dir
+        .child(".tmplr/manifest.toml")
+        .assert(predicate::str::is_match(r#"tmplr_version = "\d+\.\d+\.\d+"\n"#)?)
+        .assert(predicate::str::contains(format!(
+            "[[template]]\nname = \"{}\"\nsource = \"{}\"\nhash = \"sha256:",
+            source.display(),
+            source.display()
+        )))
+        .assert(predicate::str::contains(
+            "[template.variables]\napi_token = \"********\"\nedition = \"2024\"\nlicense = \"********\"\nname = \"core\"\n",
+        ))
+        .assert(predicate::str::contains("[template.files]\n\"src/core.rs\" = \"sha256:"))
+        .assert(predicate::str::contains("\n\"notes.txt\" = \"sha256:"))
+        .assert(predicate::str::contains("LIC-123").not())
+        .assert(predicate::str::contains("assets").not());
+    Ok(())
+}
+
+#[test]
+fn diff_and_failures_leave_no_manifest() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE README.md ###}
+# {{ name }}
+{### INSERT missing.rs after="mod" ###}
+mod x;
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--diff")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure();
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure();
+
+    unroll_dir.child(".tmplr").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn records_library_name_of_partial_match() -> TestResult {
+    let config_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let library = config_dir.child("tmplr");
+    _ = library
+        .child("rust")
+        .child("cli-app.tmplr")
+        .write_str("{### FILE README.md ###}\n# {{ name }}\n");
+    _ = library
+        .child("ci.tmplr")
+        .write_str("{### FILE ci.yml ###}\nname: CI\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("cli")
+        .arg("app")
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir
+        .child(".tmplr/manifest.toml")
+        .assert(predicate::str::contains("name = \"rust/cli-app\"\n"));
+
+    // Same template again replaces its record, another one is recorded next
+    // to it
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("cli-app")
+        .arg("app")
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("ci")
+        .arg("app")
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    let manifest = std::fs::read_to_string(unroll_dir.child(".tmplr/manifest.toml").path())?;
+    assert_eq!(manifest.matches("[[template]]").count(), 2);
+    assert!(manifest.contains("name = \"rust/cli-app\"\n"));
+    assert!(manifest.contains("name = \"ci\"\n"));
+    Ok(())
+}
+
+#[test]
+fn records_templates_with_same_file_name_apart() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let first = template_dir.child("a/setup.tmplr");
+    _ = first.write_str("{### FILE a.txt ###}\na\n");
+    let second = template_dir.child("b/setup.tmplr");
+    _ = second.write_str("{### FILE b.txt ###}\nb\n");
+
+    for template in [&first, &second] {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template.path())
+            .arg("app")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+
+    let manifest = std::fs::read_to_string(unroll_dir.child(".tmplr/manifest.toml").path())?;
+    for template in [&first, &second] {
+        let name = template.path().canonicalize()?;
+        assert!(manifest.contains(&format!("name = \"{}\"\n", name.display())));
+    }
+    Ok(())
+}
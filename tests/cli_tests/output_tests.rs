@@ -0,0 +1,116 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn writes_into_output_dir() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let work_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE src/{{ name }}.rs ###}
+pub fn {{ name }}() {}
+{### RUN ###}
+touch hook-ran
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg("-o")
+        .arg("services/billing")
+        .arg(template_path.path())
+        .arg("api")
+        .current_dir(&work_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Writing: src/api.rs"));
+
+    work_dir
+        .child("services/billing/src/api.rs")
+        .assert("pub fn api() {}\n");
+    work_dir
+        .child("services/billing/hook-ran")
+        .assert(predicate::path::exists());
+    work_dir.child("src").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn into_name_wraps_output() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let work_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE README.md ###}\n# {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--into-name")
+        .arg(format!("--output={}", work_dir.path().display()))
+        .arg(template_path.path())
+        .arg("app")
+        .assert()
+        .success();
+
+    work_dir.child("app/README.md").assert("# app\n");
+    Ok(())
+}
+
+#[test]
+fn into_name_rejects_escaping_name() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let work_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE README.md ###}\n# {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--into-name")
+        .arg(template_path.path())
+        .arg("../app")
+        .current_dir(&work_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid path `../app`: target reaches outside parent directory",
+        ));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn paths_are_checked_against_output_dir() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let work_dir = assert_fs::TempDir::new()?;
+    let outside = assert_fs::TempDir::new()?;
+
+    work_dir.child("out").create_dir_all()?;
+    std::os::unix::fs::symlink(outside.path(), work_dir.child("out/link").path())?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE link/evil.txt ###}\nowned\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-o")
+        .arg("out")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&work_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("symlink leads outside of"));
+
+    outside
+        .child("evil.txt")
+        .assert(predicate::path::missing());
+    Ok(())
+}
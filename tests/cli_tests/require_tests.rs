@@ -0,0 +1,155 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn failing_requirements_abort_before_writing() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE {{ name }}.txt ###}
+hello
+{### REQUIRE exists Cargo.toml ###}
+{### REQUIRE command surely-not-a-command-tmplr ###}
+{### REQUIRE var license /MIT|Apache-2.0/ ###}
+{### REQUIRE tmplr 999.0 ###}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .arg("license=GPL")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Template requirements not met"))
+        .stderr(predicate::str::contains("- Cargo.toml doesn't exist"))
+        .stderr(predicate::str::contains(
+            "- command `surely-not-a-command-tmplr` not found on PATH",
+        ))
+        .stderr(predicate::str::contains(
+            "- variable `license` is `GPL`, expected /MIT|Apache-2.0/",
+        ))
+        .stderr(predicate::str::contains("- tmplr 999.0 or newer required"));
+
+    unroll_dir
+        .child("TEST.txt")
+        .assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn satisfied_requirements() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("Cargo.toml").write_str("[package]\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### REQUIRE exists Cargo.toml ###}
+{### REQUIRE missing src/{{ name }}.rs ###}
+{### REQUIRE var name ###}
+{### REQUIRE tmplr 0.0.1 ###}
+{### FILE src/{{ name }}.rs ###}
+pub fn f() {}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("util")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("src/util.rs")
+        .assert(predicate::eq("pub fn f() {}\n"));
+
+    // Second run stops on `missing`
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("util")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("src/util.rs already exists"));
+    Ok(())
+}
+
+#[test]
+fn custom_message() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path
+        .write_str("{### REQUIRE exists kakrc message=\"run it in kakoune config dir\" ###}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("- run it in kakoune config dir"));
+    Ok(())
+}
+
+#[test]
+fn paths_are_checked_in_output_dir() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let work_dir = assert_fs::TempDir::new()?;
+    let output_dir = work_dir.child("out");
+
+    // Only the current dir has Cargo.toml, output dir doesn't
+    let _ = work_dir.child("Cargo.toml").write_str("[package]\n");
+    let _ = output_dir.child("README.md").write_str("# out\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### REQUIRE exists README.md ###}
+{### REQUIRE missing Cargo.toml ###}
+{### FILE src/{{ name }}.rs ###}
+pub fn f() {}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-o")
+        .arg(output_dir.path())
+        .arg(template_path.path())
+        .arg("util")
+        .current_dir(&work_dir)
+        .assert()
+        .success();
+    output_dir
+        .child("src/util.rs")
+        .assert(predicate::path::exists());
+
+    _ = template_path.write_str("{### REQUIRE exists ../Cargo.toml ###}\n");
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-o")
+        .arg(output_dir.path())
+        .arg(template_path.path())
+        .arg("util")
+        .current_dir(&work_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid path `../Cargo.toml`"));
+    Ok(())
+}
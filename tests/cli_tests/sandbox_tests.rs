@@ -0,0 +1,138 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn rejects_absolute_path_from_variable() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let outside = assert_fs::TempDir::new()?;
+    let target = outside.child("passwd");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE {{ name }} ###}\npwned\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg(target.path())
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("absolute paths aren't allowed"));
+
+    target.assert(predicate::path::missing());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn rejects_symlink_leading_outside() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let outside = assert_fs::TempDir::new()?;
+    std::os::unix::fs::symlink(outside.path(), unroll_dir.path().join("config"))?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE config/settings.toml ###}\npwned = true\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid path `config/settings.toml`: symlink leads outside of",
+        ));
+
+    outside
+        .child("settings.toml")
+        .assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn rejects_git_dir_in_template() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### FILE .git/hooks/pre-commit ###}\nrm -rf ~\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "writing into `.git` isn't allowed",
+        ));
+
+    unroll_dir.child(".git").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn rejects_reserved_names_in_template() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    for (path, name) in [("CON", "CON"), ("logs/nul.txt", "nul.txt")] {
+        let template_path = template_dir.child("some.tmplr");
+        _ = template_path.write_str(&format!("{{### FILE {} ###}}\ndata\n", path));
+
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg("app")
+            .current_dir(&unroll_dir)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "`{}` is a reserved name",
+                name
+            )));
+    }
+
+    unroll_dir.child("CON").assert(predicate::path::missing());
+    unroll_dir.child("logs").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn rejects_symlinked_rej_file() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let outside = assert_fs::TempDir::new()?;
+    unroll_dir.child("b.txt").write_str("one\n")?;
+    std::os::unix::fs::symlink(
+        outside.path().join("rej"),
+        unroll_dir.path().join("b.txt.rej"),
+    )?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### PATCH b.txt ###}\n@@ -1,1 +1,1 @@\n-two\n+three\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid path `b.txt.rej`: symlink leads outside of",
+        ));
+
+    outside.child("rej").assert(predicate::path::missing());
+    Ok(())
+}
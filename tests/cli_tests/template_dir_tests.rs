@@ -115,7 +115,7 @@ fn fails_when_multiple_partial_matches() -> TestResult {
         .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
         .current_dir(&unroll_dir)
         .assert()
-        .success()
+        .failure()
         .stderr(predicates::str::contains(
             "Error: Multiple templates matched input string",
         ))
@@ -160,3 +160,68 @@ This is contents of dir2/file.txt
 
     Ok(())
 }
+#[test]
+fn directory_template() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let library = template_dir.child("tmplr");
+    _ = library.child("single.tmplr").touch();
+    let rust_cli = library.child("rust").child("rust-cli");
+    _ = rust_cli
+        .child("tmplr.toml")
+        .write_str("description = \"Rust CLI\"\ntags = [\"rust\"]\n");
+    _ = rust_cli
+        .child("Cargo.toml")
+        .write_str("[package]\nname = \"{{ name }}\"\n");
+    _ = rust_cli
+        .child("src")
+        .child("{{ name }}.rs")
+        .write_str("pub fn {{ name }}() {}\n");
+
+    Command::new(COMMAND)
+        .arg("list")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- rust-cli: Rust CLI [rust]"))
+        .stdout(predicate::str::contains("- single.tmplr"));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("cli")
+        .arg("app")
+        .env("XDG_CONFIG_HOME", template_dir.to_str().unwrap())
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Expanding: rust/rust-cli"));
+
+    unroll_dir
+        .child("Cargo.toml")
+        .assert("[package]\nname = \"app\"\n");
+    unroll_dir
+        .child("src")
+        .child("app.rs")
+        .assert("pub fn app() {}\n");
+    unroll_dir
+        .child("tmplr.toml")
+        .assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn fails_when_template_is_missing() -> TestResult {
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(unroll_dir.child("missing.tmplr").path())
+        .arg("app")
+        .env("XDG_CONFIG_HOME", unroll_dir.path())
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: "));
+    Ok(())
+}
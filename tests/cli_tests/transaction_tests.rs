@@ -0,0 +1,101 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn failing_section_leaves_target_untouched() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("README.md").write_str("# Old\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE README.md ###}
+# {{ name }}
+{### FILE src/{{ name }}.rs ###}
+pub fn run() {}
+{### INSERT src/missing.rs after="mod" ###}
+mod {{ name }};
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Can't read file for insertion: src/missing.rs",
+        ));
+
+    unroll_dir.child("README.md").assert("# Old\n");
+    unroll_dir.child("src").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[test]
+fn sections_see_staged_files() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE src/lib.rs ###}
+mod a;
+{### EXT src/lib.rs ###}
+mod {{ name }};
+{### MOVE src -> {{ name }} ###}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg(template_path.path())
+        .arg("core")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("core/lib.rs")
+        .assert("mod a;\nmod core;\n");
+    unroll_dir.child("src").assert(predicate::path::missing());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn rewritten_file_keeps_permissions() -> TestResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let script = unroll_dir.child("run.sh");
+    script.write_str("#!/bin/sh\n")?;
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755))?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### EXT run.sh ###}\necho {{ name }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    script.assert("#!/bin/sh\necho app\n");
+    let mode = std::fs::metadata(script.path())?.permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+    Ok(())
+}
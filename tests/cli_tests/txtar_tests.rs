@@ -0,0 +1,114 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+const ARCHIVE: &str = r#"Go module fixture
+-- go.mod --
+module example.com/{{ name }}
+-- cmd/{{ name }}/main.go --
+package main
+"#;
+
+#[test]
+fn make_accepts_txtar() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let archive = template_dir.child("module.txtar");
+    _ = archive.write_str(ARCHIVE);
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(archive.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("go.mod")
+        .assert("module example.com/app\n");
+    unroll_dir.child("cmd/app/main.go").assert("package main\n");
+    Ok(())
+}
+
+#[test]
+fn converts_between_formats() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+
+    let archive = template_dir.child("module.txtar");
+    _ = archive.write_str(ARCHIVE);
+    let template = r#"Go module fixture
+{### FILE go.mod ###}
+module example.com/{{ name }}
+{### FILE cmd/{{ name }}/main.go ###}
+package main
+"#;
+
+    Command::new(COMMAND)
+        .arg("convert")
+        .arg("--from")
+        .arg("txtar")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq(template));
+
+    let template_path = template_dir.child("module.tmplr");
+    _ = template_path.write_str(&format!("{}{{### DIR empty ###}}\n", template));
+
+    Command::new(COMMAND)
+        .arg("convert")
+        .arg("--to")
+        .arg("txtar")
+        .arg(template_path.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq(ARCHIVE))
+        .stderr(predicate::str::contains(
+            "WARN: txtar only holds FILE sections, skipping DIR empty",
+        ));
+    Ok(())
+}
+
+#[test]
+fn infers_formats_from_extension() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+
+    let archive = template_dir.child("module.txtar");
+    _ = archive.write_str(ARCHIVE);
+    Command::new(COMMAND)
+        .arg("convert")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{### FILE go.mod ###}\n"));
+
+    let template_path = template_dir.child("script.tmplr");
+    _ = template_path.write_str("{### FILE run.sh mode=755 ###}\necho hi\n");
+    Command::new(COMMAND)
+        .arg("convert")
+        .arg(template_path.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq("-- run.sh --\necho hi\n"))
+        .stderr(predicate::str::contains(
+            "WARN: txtar can't hold attributes, dropping `mode=755` of FILE run.sh",
+        ));
+
+    Command::new(COMMAND)
+        .arg("convert")
+        .arg("--to")
+        .arg("tmplr")
+        .arg(template_path.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--from and --to are the same format",
+        ));
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn block_is_replaced_on_rerun() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child(".gitignore").write_str("*.log\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    for target in ["target/", "/target\nCargo.lock"] {
+        _ = template_path.write_str(&format!(
+            "{{### BLOCK .gitignore id=rust ###}}\n{}\n",
+            target
+        ));
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg("TEST")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Updating block: .gitignore (rust)",
+            ));
+    }
+
+    unroll_dir.child(".gitignore").assert(predicate::eq(
+        "*.log\n# >>> tmplr:rust\n/target\nCargo.lock\n# <<< tmplr:rust\n",
+    ));
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("identical `rust` block"));
+    Ok(())
+}
+
+#[test]
+fn block_uses_comment_syntax() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### BLOCK src/lib.rs id=mods ###}
+mod {{ name }};
+{### BLOCK notes.txt id={{ name }} comment=";;" ###}
+{{ name }}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("cli")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir.child("src/lib.rs").assert(predicate::eq(
+        "// >>> tmplr:mods\nmod cli;\n// <<< tmplr:mods\n",
+    ));
+    unroll_dir
+        .child("notes.txt")
+        .assert(predicate::eq(";; >>> tmplr:cli\ncli\n;; <<< tmplr:cli\n"));
+    Ok(())
+}
+
+#[test]
+fn block_requires_id() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### BLOCK .gitignore ###}\ntarget/\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .stderr(predicate::str::contains(
+            "BLOCK section needs `id` attribute",
+        ));
+    unroll_dir
+        .child(".gitignore")
+        .assert(predicate::path::missing());
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn prepend_to_existing_file() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("main.rs").write_str("fn main() {}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### PREPEND main.rs ###}
+// Copyright {{ name }}
+"#,
+    );
+
+    for _ in 0..2 {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg("ACME")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+
+    unroll_dir
+        .child("main.rs")
+        .assert(predicate::eq("// Copyright ACME\nfn main() {}\n"));
+    Ok(())
+}
+
+#[test]
+fn prepend_creates_missing_file() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### PREPEND lib.rs ###}\nuse std::io;\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Writing: lib.rs"));
+
+    unroll_dir
+        .child("lib.rs")
+        .assert(predicate::eq("use std::io;\n"));
+    Ok(())
+}
+
+#[test]
+fn insert_after_marker() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir
+        .child("routes.rs")
+        .write_str("fn routes(r: &mut Router) {\n    // tmplr:routes\n    r.add(home);\n}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### INSERT routes.rs after="// tmplr:routes" ###}
+r.add({{ name }});
+"#,
+    );
+
+    for _ in 0..2 {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg("users")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+
+    unroll_dir.child("routes.rs").assert(predicate::eq(
+        "fn routes(r: &mut Router) {\n    // tmplr:routes\n    r.add(users);\n    r.add(home);\n}\n",
+    ));
+    Ok(())
+}
+
+#[test]
+fn insert_after_last_regex_match() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir
+        .child("main.rs")
+        .write_str("mod a;\nmod b;\n\nfn main() {}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### INSERT main.rs after='/^mod \w+;$/' at=last ###}
+mod {{ name }};
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("handler")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Inserting: main.rs"));
+
+    unroll_dir.child("main.rs").assert(predicate::eq(
+        "mod a;\nmod b;\nmod handler;\n\nfn main() {}\n",
+    ));
+    Ok(())
+}
+
+#[test]
+fn insert_fails_without_anchor() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("main.rs").write_str("fn main() {}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### INSERT main.rs before="// tmplr:mods" ###}
+mod {{ name }};
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("handler")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Anchor line not found in main.rs"));
+
+    unroll_dir
+        .child("main.rs")
+        .assert(predicate::eq("fn main() {}\n"));
+    Ok(())
+}
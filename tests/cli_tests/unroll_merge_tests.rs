@@ -0,0 +1,129 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn merges_into_cargo_toml() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("Cargo.toml").write_str(
+        r#"[package]
+name = "app"
+
+# Runtime dependencies
+[dependencies]
+regex = "1"
+"#,
+    );
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### MERGE Cargo.toml ###}
+[dependencies]
+{{ name }} = "1"
+
+[workspace]
+members = ["{{ name }}"]
+"#,
+    );
+
+    for _ in 0..2 {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template_path.path())
+            .arg("serde")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+
+    unroll_dir.child("Cargo.toml").assert(predicate::eq(
+        r#"[package]
+name = "app"
+
+# Runtime dependencies
+[dependencies]
+regex = "1"
+serde = "1"
+
+[workspace]
+members = ["serde"]
+"#,
+    ));
+    Ok(())
+}
+
+#[test]
+fn merge_conflict_policies() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir
+        .child("package.json")
+        .write_str("{\n  \"scripts\": {\n    \"test\": \"jest\"\n  }\n}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### MERGE package.json conflict=error ###}
+{ "scripts": { "test": "vitest" } }
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Conflicting value for `scripts.test`",
+        ));
+
+    _ = template_path.write_str(
+        r#"{### MERGE package.json conflict=override ###}
+{ "scripts": { "test": "vitest" } }
+"#,
+    );
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Merging: package.json"));
+
+    unroll_dir.child("package.json").assert(predicate::eq(
+        "{\n  \"scripts\": {\n    \"test\": \"vitest\"\n  }\n}\n",
+    ));
+    Ok(())
+}
+
+#[test]
+fn merge_creates_missing_file() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### MERGE config/app.yml ###}\nname: {{ name }}\n");
+    let _ = unroll_dir.child("config").create_dir_all();
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("demo")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("config/app.yml")
+        .assert(predicate::eq("name: demo\n"));
+    Ok(())
+}
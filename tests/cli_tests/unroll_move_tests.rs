@@ -0,0 +1,132 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn remove_and_move_with_yes() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("old/config.yml").write_str("a: 1\n");
+    let _ = unroll_dir
+        .child("src/lib.rs")
+        .write_str("pub fn lib() {}\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### REMOVE old/config.yml ###}
+{### MOVE src/lib.rs -> src/{{ name }}/mod.rs ###}
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg(template_path.path())
+        .arg("core")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removing: old/config.yml"))
+        .stdout(predicate::str::contains(
+            "Moving: src/lib.rs -> src/core/mod.rs",
+        ));
+
+    unroll_dir
+        .child("old/config.yml")
+        .assert(predicate::path::missing());
+    unroll_dir
+        .child("src/lib.rs")
+        .assert(predicate::path::missing());
+    unroll_dir
+        .child("src/core/mod.rs")
+        .assert(predicate::eq("pub fn lib() {}\n"));
+
+    // Running again is a no-op
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-y")
+        .arg(template_path.path())
+        .arg("core")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("nothing to remove"))
+        .stderr(predicate::str::contains("already moved"));
+    Ok(())
+}
+
+#[test]
+fn remove_needs_confirmation() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("old.txt").write_str("old\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### REMOVE old.txt ###}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use --yes"));
+
+    unroll_dir.child("old.txt").assert(predicate::eq("old\n"));
+    Ok(())
+}
+
+#[test]
+fn dry_run_shows_remove_and_move() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("a.txt").write_str("a\n");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### REMOVE a.txt ###}\n{### MOVE a.txt {{ name }}.txt ###}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("-n")
+        .arg(template_path.path())
+        .arg("b")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{### REMOVE a.txt ###}"))
+        .stdout(predicate::str::contains("{### MOVE a.txt -> b.txt ###}"));
+
+    unroll_dir.child("a.txt").assert(predicate::eq("a\n"));
+    Ok(())
+}
+
+#[test]
+fn rejects_paths_outside_target() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### REMOVE ../secret.txt ###}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg("--yes")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .stderr(predicate::str::contains("outside parent directory"));
+
+    template_dir
+        .child("secret.txt")
+        .assert(predicate::path::missing());
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+const MAIN_RS: &str =
+    "use std::env;\n\nfn main() {\n    let args = env::args();\n    run(args);\n}\n";
+
+#[test]
+fn patch_applies_with_variables() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir
+        .child("src/main.rs")
+        .write_str(&format!("// added by user\n{}", MAIN_RS));
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### PATCH src/main.rs ###}
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -3,4 +3,5 @@
+ fn main() {
+     let args = env::args();
+-    run(args);
++    {{ name }}::init();
++    {{ name }}::run(args);
+ }
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Patching: src/main.rs"));
+
+    unroll_dir.child("src/main.rs").assert(predicate::eq(
+        "// added by user\nuse std::env;\n\nfn main() {\n    let args = env::args();\n    app::init();\n    app::run(args);\n}\n",
+    ));
+    unroll_dir
+        .child("src/main.rs.rej")
+        .assert(predicate::path::exists().not());
+
+    // Applied hunks are recognized on the second run
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipping: src/main.rs (1 out of 1 hunks already applied)",
+        ))
+        .stdout(predicate::str::contains("Patching").not());
+    unroll_dir
+        .child("src/main.rs")
+        .assert(predicate::str::contains("app::init();\n    app::run").count(1));
+    unroll_dir
+        .child("src/main.rs.rej")
+        .assert(predicate::path::exists().not());
+    Ok(())
+}
+
+#[test]
+fn failed_hunk_writes_rej() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("src/main.rs").write_str(MAIN_RS);
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### PATCH src/main.rs ###}
+@@ -1,1 +1,2 @@
+ use std::env;
++use std::io;
+@@ -10,3 +11,3 @@
+ fn other() {
+-    old();
++    new();
+ }
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "1 out of 2 hunks FAILED for src/main.rs -- saving all hunks to src/main.rs.rej",
+        ));
+
+    // Hunks that did apply are rolled back with everything else, so they
+    // are saved along with the failed one
+    unroll_dir.child("src/main.rs").assert(MAIN_RS);
+    unroll_dir.child("src/main.rs.rej").assert(
+        predicate::str::contains(" use std::env;\n+use std::io;")
+            .and(predicate::str::contains("-    old();\n+    new();")),
+    );
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn merges_template_changes_with_local_changes() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("service.tmplr");
+    _ = template_path.write_str(
+        r#"{### FILE config.txt ###}
+name = {{ name }}
+port = 8080
+host = localhost
+log = info
+{### FILE README.md ###}
+# {{ name }}
+{### FILE untouched.txt ###}
+v1
+"#,
+    );
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("billing")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir
+        .child(".tmplr/base/config.txt")
+        .assert("name = billing\nport = 8080\nhost = localhost\nlog = info\n");
+
+    _ = unroll_dir
+        .child("config.txt")
+        .write_str("name = billing\nport = 9090\nhost = localhost\nlog = info\n");
+    _ = unroll_dir
+        .child("README.md")
+        .write_str("# Billing service\n");
+
+    _ = template_path.write_str(
+        r#"{### FILE config.txt ###}
+name = {{ name }}
+port = 8080
+host = localhost
+log = warn
+{### FILE README.md ###}
+# {{ name }} service
+{### FILE untouched.txt ###}
+v2
+"#,
+    );
+    Command::new(COMMAND)
+        .arg("update")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("README.md: 1 conflicting changes"));
+
+    unroll_dir
+        .child("config.txt")
+        .assert("name = billing\nport = 9090\nhost = localhost\nlog = warn\n");
+    unroll_dir.child("untouched.txt").assert("v2\n");
+    unroll_dir
+        .child("README.md")
+        .assert("<<<<<<< local\n# Billing service\n=======\n# billing service\n>>>>>>> template\n");
+    unroll_dir
+        .child(".tmplr/base/README.md")
+        .assert("# billing service\n");
+    Ok(())
+}
+
+#[test]
+fn needs_secrets_and_manifest() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    Command::new(COMMAND)
+        .arg("update")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(".tmplr/manifest.toml not found"));
+
+    let template_path = template_dir.child("app.tmplr");
+    _ = template_path.write_str(
+        r#"{### VAR token secret=true ###}
+{### FILE .env ###}
+TOKEN={{ token }}
+"#,
+    );
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .arg("token=s3cret-one")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    Command::new(COMMAND)
+        .arg("update")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pass token=VALUE"));
+    Command::new(COMMAND)
+        .arg("update")
+        .arg("token=s3cret-two")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir.child(".env").assert("TOKEN=s3cret-two\n");
+    for secret in ["s3cret-one", "s3cret-two"] {
+        assert!(!contains_recursive(
+            &unroll_dir.path().join(".tmplr"),
+            secret
+        )?);
+    }
+    Ok(())
+}
+
+fn contains_recursive(dir: &std::path::Path, needle: &str) -> std::io::Result<bool> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let found = match path.is_dir() {
+            true => contains_recursive(&path, needle)?,
+            false => std::fs::read_to_string(&path)?.contains(needle),
+        };
+        if found {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[test]
+fn edits_are_not_applied_again() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+    _ = unroll_dir.child("README.md").write_str("# Project\n");
+    _ = unroll_dir.child(".gitignore").write_str("*.log\n");
+
+    let template = |edition: &str| {
+        format!(
+            r#"{{### FILE Cargo.toml ###}}
+[package]
+edition = "{}"
+name = "{{{{ name }}}}"
+{{### EXT Cargo.toml ###}}
+[dependencies]
+{{### EXT .gitignore ###}}
+target/
+{{### PATCH README.md ###}}
+@@ -1,1 +1,2 @@
+ # Project
++Made by tmplr.
+{{### RUN ###}}
+echo made
+"#,
+            edition
+        )
+    };
+    let template_path = template_dir.child("crate.tmplr");
+    _ = template_path.write_str(&template("2021"));
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .arg("--yes")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    let gitignore = std::fs::read_to_string(unroll_dir.child(".gitignore").path())?;
+    let manifest = unroll_dir.child(".tmplr/manifest.toml");
+    let entry = |file: &str| -> std::io::Result<String> {
+        let text = std::fs::read_to_string(manifest.path())?;
+        Ok(text
+            .lines()
+            .find(|line| line.starts_with(&format!("\"{}\" = ", file)))
+            .unwrap_or_default()
+            .to_string())
+    };
+    let entries = (entry(".gitignore")?, entry("README.md")?);
+    assert!(entries.0.contains("sha256:") && entries.1.contains("sha256:"));
+    unroll_dir
+        .child("README.md")
+        .assert("# Project\nMade by tmplr.\n");
+
+    _ = template_path.write_str(&template("2024"));
+    Command::new(COMMAND)
+        .arg("update")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipping: .gitignore (edited by make already)",
+        ))
+        .stdout(predicate::str::contains(
+            "Skipping: README.md (edited by make already)",
+        ))
+        .stdout(predicate::str::contains(
+            "Skipping hooks: run by make already",
+        ));
+
+    unroll_dir
+        .child("Cargo.toml")
+        .assert("[package]\nedition = \"2024\"\nname = \"app\"\n[dependencies]\n");
+    unroll_dir.child(".gitignore").assert(gitignore.as_str());
+    unroll_dir
+        .child("README.md")
+        .assert("# Project\nMade by tmplr.\n");
+    // Files edited by skipped sections stay recorded
+    assert_eq!((entry(".gitignore")?, entry("README.md")?), entries);
+    Ok(())
+}
+
+#[test]
+fn updates_every_recorded_template() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let crate_template = |edition: &str| {
+        format!(
+            "{{### FILE Cargo.toml ###}}\n[package]\nedition = \"{}\"\nname = \"{{{{ name }}}}\"\n",
+            edition
+        )
+    };
+    let ci_template = |step: &str| {
+        format!(
+            "{{### EXT Cargo.toml ###}}\n[lints]\n{{### FILE ci.yml ###}}\nsteps: [{}]\n",
+            step
+        )
+    };
+    let crate_path = template_dir.child("crate.tmplr");
+    _ = crate_path.write_str(&crate_template("2021"));
+    let ci_path = template_dir.child("ci.tmplr");
+    _ = ci_path.write_str(&ci_template("build"));
+    for template in [&crate_path, &ci_path] {
+        Command::new(COMMAND)
+            .arg("make")
+            .arg(template.path())
+            .arg("app")
+            .current_dir(&unroll_dir)
+            .assert()
+            .success();
+    }
+
+    _ = crate_path.write_str(&crate_template("2024"));
+    _ = ci_path.write_str(&ci_template("test"));
+    Command::new(COMMAND)
+        .arg("update")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    // What the second template added is a local change to the first one
+    unroll_dir
+        .child("Cargo.toml")
+        .assert("[package]\nedition = \"2024\"\nname = \"app\"\n[lints]\n");
+    unroll_dir.child("ci.yml").assert("steps: [test]\n");
+    Ok(())
+}
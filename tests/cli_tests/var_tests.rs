@@ -0,0 +1,69 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn declared_variables_take_defaults() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        r#"{### VAR crate default="{{ name }}-cli" ###}
+{### VAR edition choices=2021,2024 default=2024 ###}
+{### FILE Cargo.toml ###}
+name = "{{ crate }}"
+edition = "{{ edition }}"
+"#,
+    );
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+    unroll_dir
+        .child("Cargo.toml")
+        .assert("name = \"app-cli\"\nedition = \"2024\"\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .arg("edition=2018")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Variable `edition` is `2018`, expected one of: 2021, 2024",
+        ));
+    Ok(())
+}
+
+#[test]
+fn declared_variable_without_default_is_required() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### VAR owner ###}\n{### FILE a.txt ###}\n{{ owner }}\n");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path.path())
+        .arg("app")
+        .current_dir(&unroll_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Variable `owner` is not set, pass owner=VALUE",
+        ));
+    unroll_dir.child("a.txt").assert(predicate::path::missing());
+    Ok(())
+}
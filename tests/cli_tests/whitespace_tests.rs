@@ -0,0 +1,153 @@
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const COMMAND: &str = env!("CARGO_BIN_EXE_tmplr");
+
+#[test]
+fn preserves_whitespace_exactly() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        "{### FILE a.txt ###}\n\n    indented {{ name }}\n{### FILE b.txt ###}\nno newline{### FILE c.txt ###}\nlast\n",
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("a.txt")
+        .assert(predicate::eq("\n    indented TEST\n"));
+    unroll_dir
+        .child("b.txt")
+        .assert(predicate::eq("no newline"));
+    unroll_dir.child("c.txt").assert(predicate::eq("last\n"));
+    Ok(())
+}
+
+#[test]
+fn trim_and_final_newline_attributes() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str(
+        "{### FILE trimmed.txt trim=true ###}\n\n  body  \n\n{### FILE nl.txt final_newline=true ###}\nbody{### FILE no_nl.txt final_newline=false ###}\nbody\n\n",
+    );
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("trimmed.txt")
+        .assert(predicate::eq("body"));
+    unroll_dir.child("nl.txt").assert(predicate::eq("body\n"));
+    unroll_dir.child("no_nl.txt").assert(predicate::eq("body"));
+    Ok(())
+}
+
+#[test]
+fn extend_keeps_final_newline() -> TestResult {
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let _ = unroll_dir.child("list.txt").write_str("one");
+
+    let template_path = template_dir.child("some.tmplr");
+    _ = template_path.write_str("{### EXT list.txt ###}\ntwo\n");
+
+    let mut cmd = Command::new(COMMAND);
+    cmd.arg("make")
+        .arg(template_path.path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir
+        .child("list.txt")
+        .assert(predicate::eq("one\ntwo\n"));
+    Ok(())
+}
+
+#[test]
+fn create_make_round_trip() -> TestResult {
+    let source_dir = assert_fs::TempDir::new()?;
+    let template_dir = assert_fs::TempDir::new()?;
+    let unroll_dir = assert_fs::TempDir::new()?;
+
+    let files = [
+        ("trailing.txt", "line\n"),
+        ("no_trailing.txt", "line"),
+        ("blank_lines.txt", "\n\n  indented\n\n"),
+        ("empty.txt", ""),
+        ("sub/tabs.txt", "\tTEST\t\n"),
+    ];
+    for (path, content) in files {
+        _ = source_dir.child(path).write_str(content);
+    }
+
+    Command::new(COMMAND)
+        .arg("create")
+        .arg("TEST")
+        .arg("-C")
+        .arg(source_dir.path())
+        .current_dir(&template_dir)
+        .assert()
+        .success();
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_dir.child("TEST.tmplr").path())
+        .arg("TEST")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    for (path, content) in files {
+        unroll_dir.child(path).assert(predicate::eq(content));
+    }
+    Ok(())
+}
+
+#[test]
+fn bundled_template_has_no_stray_blank_lines() -> TestResult {
+    let unroll_dir = assert_fs::TempDir::new()?;
+    let template_path = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/rust/exercise.tmplr");
+
+    Command::new(COMMAND)
+        .arg("make")
+        .arg(template_path)
+        .arg("ex")
+        .current_dir(&unroll_dir)
+        .assert()
+        .success();
+
+    unroll_dir.child("ex/.gitignore").assert("/target\n");
+    unroll_dir.child("ex/Cargo.toml").assert(
+        "[package]\nname = \"ex\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\n",
+    );
+    for file in ["Makefile", "Cargo.lock", "src/main.rs"] {
+        unroll_dir
+            .child("ex")
+            .child(file)
+            .assert(predicate::str::starts_with("\n").not())
+            .assert(predicate::str::ends_with("\n"))
+            .assert(predicate::str::ends_with("\n\n").not());
+    }
+    Ok(())
+}